@@ -0,0 +1,167 @@
+//! Compares the entity count and frame time of each [`TileRendering`] mode.
+//!
+//! The map is rendered off-screen by the headless renderer, so a GPU is needed.
+//! Pass `--cpu-only` to skip rendering and only measure the work done by the ECS.
+//!
+//! ```text
+//! cargo bench --bench tile_rendering
+//! cargo bench --bench tile_rendering -- --cpu-only
+//! ```
+
+use bevy::{
+    asset::AssetMetaCheck,
+    audio::AudioPlugin,
+    log::LogPlugin,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        settings::{RenderCreation, WgpuSettings},
+        RenderPlugin,
+    },
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use bevy_template::game::{
+    map_change::{self, AssetData, Kare, LoadedChunks, PendingChunks},
+    spawn::player::Player,
+    tile_mesh::TileRendering,
+    tileset,
+};
+use bevy_template::mapgen::{MapSeed, WFCRules};
+use std::time::{Duration, Instant};
+
+/// Frames measured per mode, after the map has been spawned.
+const FRAMES: usize = 300;
+
+fn main() {
+    let cpu_only = std::env::args().any(|arg| arg == "--cpu-only");
+    println!(
+        "{:<8} {:>9} {:>13} {:>12} {:>12}",
+        "mode", "entities", "mesh entities", "mean frame", "worst frame"
+    );
+    for rendering in [TileRendering::Scenes, TileRendering::Merged] {
+        let mut app = app(rendering, cpu_only);
+        wait_for_map(&mut app);
+
+        let mut frame_times = Vec::with_capacity(FRAMES);
+        for _ in 0..FRAMES {
+            let start = Instant::now();
+            app.update();
+            frame_times.push(start.elapsed());
+        }
+        let mean = frame_times.iter().sum::<Duration>() / FRAMES as u32;
+        let worst = frame_times.iter().max().copied().unwrap_or_default();
+
+        let world = app.world_mut();
+        let entities = world.entities().len();
+        let meshes = world
+            .query_filtered::<(), With<Handle<Mesh>>>()
+            .iter(world)
+            .count();
+        println!(
+            "{:<8} {entities:>9} {meshes:>13} {:>12} {:>12}",
+            format!("{rendering:?}"),
+            format!("{mean:.2?}"),
+            format!("{worst:.2?}"),
+        );
+    }
+}
+
+fn app(rendering: TileRendering, cpu_only: bool) -> App {
+    let render_creation = if cpu_only {
+        RenderCreation::Automatic(WgpuSettings {
+            backends: None,
+            ..default()
+        })
+    } else {
+        RenderCreation::default()
+    };
+
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .set(AssetPlugin {
+                meta_check: AssetMetaCheck::Never,
+                ..default()
+            })
+            .set(RenderPlugin {
+                render_creation,
+                ..default()
+            })
+            .disable::<WinitPlugin>()
+            .disable::<AudioPlugin>()
+            .disable::<LogPlugin>(),
+    )
+    .add_plugins((tileset::plugin, map_change::plugin))
+    .insert_resource(Kare::new(64, 4).unwrap())
+    .insert_resource(MapSeed(1))
+    .insert_resource(rendering);
+    app.finish();
+    app.cleanup();
+
+    let size = Extent3d {
+        width: 1280,
+        height: 720,
+        ..default()
+    };
+    let mut image = Image::new_fill(
+        size,
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::Bgra8UnormSrgb,
+        default(),
+    );
+    image.texture_descriptor.usage |= TextureUsages::RENDER_ATTACHMENT;
+    let target = app.world_mut().resource_mut::<Assets<Image>>().add(image);
+
+    app.world_mut().spawn((Player, SpatialBundle::default()));
+    app.world_mut().spawn(Camera3dBundle {
+        camera: Camera {
+            target: RenderTarget::Image(target),
+            ..default()
+        },
+        transform: Transform::from_xyz(0.0, 80.0, 80.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+    app.world_mut().spawn(DirectionalLightBundle {
+        transform: Transform::from_xyz(1.0, 2.0, 1.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+    app
+}
+
+/// Runs the app until the tileset and its models have loaded and every chunk in
+/// view has been spawned.
+fn wait_for_map(app: &mut App) {
+    let timeout = Instant::now() + Duration::from_secs(120);
+    let ready = |world: &World| {
+        let loaded = world.resource::<WFCRules>().tile_count() > 0
+            && world
+                .resource::<AssetData>()
+                .is_loaded(world.resource::<AssetServer>());
+        loaded
+            && world.resource::<PendingChunks>().is_empty()
+            && !world.resource::<LoadedChunks>().is_empty()
+    };
+    while !ready(app.world()) {
+        assert!(Instant::now() < timeout, "the map did not load in time");
+        app.update();
+    }
+    // Let the map be rebuilt with the loaded models and the scenes be spawned.
+    for _ in 0..10 {
+        app.update();
+    }
+    while !app.world().resource::<PendingChunks>().is_empty() {
+        assert!(Instant::now() < timeout, "the map did not load in time");
+        app.update();
+    }
+    for _ in 0..30 {
+        app.update();
+    }
+}
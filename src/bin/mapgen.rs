@@ -0,0 +1,145 @@
+//! Generates a part of the world without starting the game.
+//!
+//! ```text
+//! cargo run --bin mapgen -- --seed 42 --width 64 --depth 32
+//! cargo run --bin mapgen -- --seed 42 --format png --output map.png
+//! ```
+
+use bevy::math::IVec2;
+use bevy_template::mapgen::{self, output, tileset::Tileset, MapSeed, WFCRules};
+use std::{fmt::Display, fs::File, io::BufWriter, process::ExitCode, str::FromStr};
+
+const USAGE: &str = "\
+Usage: mapgen [options]
+
+Options:
+  --seed <n>        seed of the world [default: random]
+  --x <n>           first cell along x [default: 0]
+  --z <n>           first cell along z [default: 0]
+  --width <n>       cells along x [default: 64]
+  --depth <n>       cells along z [default: 32]
+  --format <f>      ascii, csv or png [default: ascii]
+  --scale <n>       pixels per tile of png images [default: 4]
+  --output <path>   file to write to [default: standard output, required for png]
+  --tileset <path>  tileset to generate from [default: assets/tilesets/default.tileset.ron]
+  --help            print this message";
+
+enum Format {
+    Ascii,
+    Csv,
+    Png,
+}
+
+struct Options {
+    seed: MapSeed,
+    origin: IVec2,
+    width: usize,
+    depth: usize,
+    format: Format,
+    scale: u32,
+    output: Option<String>,
+    tileset: String,
+}
+
+fn main() -> ExitCode {
+    let options = match parse(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("error: {error}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(options: Options) -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(&options.tileset)
+        .map_err(|error| format!("could not read {}: {error}", options.tileset))?;
+    let tileset = Tileset::from_ron(&bytes)?;
+    let rules = WFCRules::from_tileset(&tileset);
+    let grid = mapgen::generate_area(
+        &rules,
+        options.seed,
+        options.origin,
+        options.width,
+        options.depth,
+    )?;
+
+    let text = match options.format {
+        Format::Ascii => output::ascii(&grid, &tileset),
+        Format::Csv => output::csv(&grid),
+        Format::Png => {
+            let path = options.output.ok_or("png images need an --output file")?;
+            let file = BufWriter::new(File::create(path)?);
+            return Ok(output::png(&grid, &tileset, options.scale, file)?);
+        }
+    };
+    match options.output {
+        Some(path) => std::fs::write(path, text)?,
+        None => print!("{text}"),
+    }
+    Ok(())
+}
+
+/// Parses the command line. Returns `None` if help was requested.
+fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options {
+        seed: MapSeed(rand::random()),
+        origin: IVec2::ZERO,
+        width: 64,
+        depth: 32,
+        format: Format::Ascii,
+        scale: 4,
+        output: None,
+        tileset: "assets/tilesets/default.tileset.ron".to_string(),
+    };
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            return Ok(None);
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {arg}"))?;
+        match arg.as_str() {
+            "--seed" => options.seed = MapSeed(number(&arg, &value)?),
+            "--x" => options.origin.x = number(&arg, &value)?,
+            "--z" => options.origin.y = number(&arg, &value)?,
+            "--width" => options.width = number::<usize>(&arg, &value)?.max(1),
+            "--depth" => options.depth = number::<usize>(&arg, &value)?.max(1),
+            "--scale" => options.scale = number::<u32>(&arg, &value)?.max(1),
+            "--format" => {
+                options.format = match value.as_str() {
+                    "ascii" => Format::Ascii,
+                    "csv" => Format::Csv,
+                    "png" => Format::Png,
+                    _ => return Err(format!("unknown format `{value}`")),
+                }
+            }
+            "--output" => options.output = Some(value),
+            "--tileset" => options.tileset = value,
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+    Ok(Some(options))
+}
+
+/// Parses the `value` of option `arg`. Values that do not fit in `T` are rejected
+/// rather than wrapped around.
+fn number<T: FromStr>(arg: &str, value: &str) -> Result<T, String>
+where
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|error| format!("{arg} expects a number, got `{value}`: {error}"))
+}
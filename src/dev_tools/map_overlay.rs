@@ -0,0 +1,179 @@
+//! An overhead view of the generated map, drawn with gizmos over the loaded chunks.
+//!
+//! Every cell is outlined in the colour of its tile, as in the PNG
+//! [`output`](crate::mapgen::output), next to the chunk borders and the view radius
+//! of the player. Chunks that had to be retried or could not fit their neighbours
+//! are outlined by their [`ChunkFit`], and cells whose tile does not fit a neighbour
+//! are crossed out. Press [`TOGGLE_KEY`] or use the inspector to toggle it.
+
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_inspector_egui::quick::ResourceInspectorPlugin;
+use std::collections::HashMap;
+
+use crate::game::{
+    lod::TileLod,
+    map_change::{Chunk, ChunkTiles, Kare},
+    spawn::player::Player,
+    terrain::ChunkGround,
+    tileset::{TilesetAsset, TilesetHandle},
+};
+use crate::mapgen::{output, ChunkFit, TileType, WFCRules, CHUNK_SIZE};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_gizmo_group::<MapOverlayGizmos>()
+        .register_type::<MapOverlay>()
+        .init_resource::<MapOverlay>()
+        .add_plugins(ResourceInspectorPlugin::<MapOverlay>::default())
+        .add_systems(Startup, configure_gizmos)
+        .add_systems(
+            Update,
+            (
+                toggle_overlay.run_if(input_just_pressed(TOGGLE_KEY)),
+                draw_overlay.run_if(|overlay: Res<MapOverlay>| overlay.enabled),
+            )
+                .chain(),
+        );
+}
+
+/// What the map overlay draws.
+#[derive(Reflect, Resource, Clone, Copy, PartialEq, Debug)]
+#[reflect(Resource)]
+pub struct MapOverlay {
+    pub enabled: bool,
+    /// Outline every cell in the colour of its tile.
+    pub tiles: bool,
+    pub chunk_borders: bool,
+    /// Circles around the player at the view radius and where each [`Lod`](crate::game::lod::Lod) starts.
+    pub view_radius: bool,
+    /// Outline the chunks that were retried or could not fit their neighbours,
+    /// and cross out the cells whose tile does not fit a neighbour.
+    pub contradictions: bool,
+}
+
+impl Default for MapOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tiles: true,
+            chunk_borders: true,
+            view_radius: true,
+            contradictions: true,
+        }
+    }
+}
+
+/// Shows or hides the overlay.
+const TOGGLE_KEY: KeyCode = KeyCode::F3;
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct MapOverlayGizmos;
+
+/// Height above the ground the overlay is drawn at.
+const LIFT: f32 = 0.1;
+const BORDER_COLOR: Color = Color::WHITE;
+const VIEW_COLOR: Color = Color::srgb(0.2, 0.6, 1.0);
+const LOD_COLOR: Color = Color::srgb(0.2, 0.9, 0.9);
+const RETRIED_COLOR: Color = Color::srgb(1.0, 0.6, 0.1);
+const CONTRADICTION_COLOR: Color = Color::srgb(1.0, 0.1, 0.1);
+
+/// Draws the overlay over the tiles rather than hiding it inside them.
+fn configure_gizmos(mut config_store: ResMut<GizmoConfigStore>) {
+    let (config, _) = config_store.config_mut::<MapOverlayGizmos>();
+    config.depth_bias = -1.0;
+}
+
+fn toggle_overlay(mut overlay: ResMut<MapOverlay>) {
+    overlay.enabled = !overlay.enabled;
+}
+
+fn draw_overlay(
+    mut gizmos: Gizmos<MapOverlayGizmos>,
+    overlay: Res<MapOverlay>,
+    kare: Res<Kare>,
+    tile_lod: Res<TileLod>,
+    rules: Res<WFCRules>,
+    handle: Res<TilesetHandle>,
+    tilesets: Res<Assets<TilesetAsset>>,
+    chunks: Query<(&Chunk, &ChunkTiles, &ChunkGround, &GlobalTransform)>,
+    player: Query<&GlobalTransform, With<Player>>,
+) {
+    if !kare.is_valid() {
+        return;
+    }
+    let tile_length = kare.kare_kenar_uzunlugu as f32;
+    let colors = tilesets
+        .get(&handle.0)
+        .map(|tileset| output::tile_colors(tileset))
+        .unwrap_or_default();
+    // The tile and ground height of every loaded cell, in global cell coordinates.
+    let mut cells = HashMap::new();
+
+    for (chunk, tiles, ground, transform) in &chunks {
+        let origin = transform.translation();
+        let ground_at = |position: Vec2| {
+            origin + Vec3::new(position.x, ground.height_at(position) + LIFT, position.y)
+        };
+        for (x, z, tile) in tiles.grid.iter() {
+            let position = Vec2::new(x as f32, z as f32) * tile_length;
+            let center = ground_at(position);
+            let cell = chunk.coord * CHUNK_SIZE as i32 + IVec2::new(x as i32, z as i32);
+            cells.insert(cell, (TileType::from_id(tile), center));
+            if overlay.tiles {
+                if let Some(&color) = colors.get(tile) {
+                    gizmos.rect(
+                        center,
+                        Quat::from_rotation_x(std::f32::consts::FRAC_PI_2),
+                        Vec2::splat(tile_length * 0.8),
+                        color,
+                    );
+                }
+            }
+        }
+        let (border_color, flagged) = match chunk.fit {
+            ChunkFit::Seamless => (BORDER_COLOR, false),
+            ChunkFit::Retried => (RETRIED_COLOR, true),
+            ChunkFit::Unconstrained => (CONTRADICTION_COLOR, true),
+        };
+        if overlay.chunk_borders || (overlay.contradictions && flagged) {
+            // Follow the ground along the border, one point per cell corner.
+            let length = CHUNK_SIZE as f32 * tile_length;
+            let border = (0..4 * CHUNK_SIZE + 1).map(|step| {
+                let along = (step % CHUNK_SIZE) as f32 * tile_length;
+                let corner = match (step / CHUNK_SIZE) % 4 {
+                    0 => Vec2::new(along, 0.0),
+                    1 => Vec2::new(length, along),
+                    2 => Vec2::new(length - along, length),
+                    _ => Vec2::new(0.0, length - along),
+                };
+                ground_at(corner - tile_length / 2.0)
+            });
+            gizmos.linestrip(border, border_color);
+        }
+    }
+
+    if overlay.contradictions && rules.tile_count() > 0 {
+        let tiles = cells
+            .iter()
+            .map(|(&cell, &(tile, _))| (cell, tile))
+            .collect();
+        let half = tile_length * 0.4;
+        for cell in rules.contradictions(&tiles) {
+            let center = cells[&cell].1;
+            for corner in [Vec3::new(half, 0.0, half), Vec3::new(half, 0.0, -half)] {
+                gizmos.line(center - corner, center + corner, CONTRADICTION_COLOR);
+            }
+        }
+    }
+
+    if overlay.view_radius {
+        let Ok(player) = player.get_single() else {
+            return;
+        };
+        let center = player.translation();
+        let view_radius = kare.view_radius();
+        gizmos.circle(center, Dir3::Y, view_radius, VIEW_COLOR);
+        for fraction in [tile_lod.near, tile_lod.mid] {
+            gizmos.circle(center, Dir3::Y, fraction * view_radius, LOD_COLOR);
+        }
+    }
+}
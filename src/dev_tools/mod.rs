@@ -1,18 +1,31 @@
 //! Development tools for the game. This plugin is only enabled in dev builds.
 
+mod map_overlay;
+
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::{dev_tools::states::log_transitions, prelude::*};
 use bevy_inspector_egui::quick::ResourceInspectorPlugin;
 
-use crate::game::map_change::{AssetData, Kare};
+use crate::game::{
+    lod::TileLod,
+    map_change::{AssetData, Kare},
+    movement::BikeTuning,
+    tile_mesh::TileRendering,
+};
+use crate::mapgen::MapSeed;
 use crate::screen::Screen;
 
 pub(super) fn plugin(app: &mut App) {
     // Print state transitions in dev builds
     app.add_systems(Update, log_transitions::<Screen>)
-        .add_plugins(FrameTimeDiagnosticsPlugin::default())
+        .add_plugins(FrameTimeDiagnosticsPlugin)
         .add_plugins(LogDiagnosticsPlugin::default())
         .add_plugins(ResourceInspectorPlugin::<Kare>::default())
+        .add_plugins(ResourceInspectorPlugin::<MapSeed>::default())
+        .add_plugins(ResourceInspectorPlugin::<TileRendering>::default())
+        .add_plugins(ResourceInspectorPlugin::<TileLod>::default())
+        .add_plugins(ResourceInspectorPlugin::<BikeTuning>::default())
+        .add_plugins(map_overlay::plugin)
         .init_resource::<Kare>()
         .init_resource::<AssetData>();
 }
@@ -1,16 +1,28 @@
 use bevy::{audio::PlaybackMode, prelude::*};
-use rand::prelude::SliceRandom;
+use rand::{prelude::SliceRandom, rngs::StdRng, Rng};
+
+use crate::mapgen::MapSeed;
+
+/// Random stream of [`MapSeed`] used to pick step sounds.
+const STEP_STREAM: u64 = u64::MAX;
 
 pub(super) fn play_sfx(
     trigger: Trigger<Sfx>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    seed: Res<MapSeed>,
+    mut step_rng: Local<Option<StdRng>>,
 ) {
+    if seed.is_changed() {
+        *step_rng = None;
+    }
+    let step_rng = step_rng.get_or_insert_with(|| seed.rng(STEP_STREAM));
+
     let event = trigger.event();
     let path = match event {
         Sfx::ButtonHover => "audio/sfx/button_hover.ogg",
         Sfx::ButtonPress => "audio/sfx/button_press.ogg",
-        Sfx::Step => random_step(),
+        Sfx::Step => random_step(step_rng),
     };
     let source = asset_server.load::<AudioSource>(path);
     let settings = PlaybackSettings {
@@ -28,13 +40,13 @@ pub enum Sfx {
     Step,
 }
 
-fn random_step() -> &'static str {
+fn random_step(rng: &mut impl Rng) -> &'static str {
     [
         "audio/sfx/step1.ogg",
         "audio/sfx/step2.ogg",
         "audio/sfx/step3.ogg",
         "audio/sfx/step4.ogg",
     ]
-    .choose(&mut rand::thread_rng())
+    .choose(rng)
     .unwrap()
 }
@@ -0,0 +1,254 @@
+//! Makes the motorbike model lean into turns, spin its wheels and bob on its
+//! suspension.
+//!
+//! All of it is applied to the [`BikeBody`], a child of the player's model, so the
+//! collider of the player stays upright and the physics never see it. The wheels
+//! are the nodes of the model named like [`WHEEL_NODES`], and each spins around
+//! the center of its own meshes.
+
+use bevy::{math::Affine3A, prelude::*, render::primitives::Aabb, scene::SceneInstanceReady};
+use std::f32::consts::TAU;
+
+use super::movement::{Bike, BikeInput, BikeTuning, VerticalVelocity};
+
+pub fn plugin(app: &mut App) {
+    app.register_type::<BikeVisuals>();
+    app.add_systems(
+        Update,
+        (
+            find_wheels,
+            warn_about_missing_wheels.run_if(resource_exists::<Events<SceneInstanceReady>>),
+            measure_wheels,
+            update_bike_visuals,
+            place_bike_bodies,
+            spin_wheels,
+        )
+            .chain(),
+    );
+}
+
+/// Prefixes of the names of the nodes of the model that are its wheels.
+pub const WHEEL_NODES: [&str; 2] = ["front_wheel", "rear_wheel"];
+
+/// Highest lean angle, in radians.
+const MAX_LEAN: f32 = 0.6;
+/// How hard the bike has to turn, as its speed times its turn rate, to lean 45°.
+const LEAN_TURN: f32 = 120.0;
+/// How quickly the lean catches up with the turn, per second.
+const LEAN_RESPONSE: f32 = 6.0;
+
+/// How stiff the suspension is, per second squared.
+const SUSPENSION_STIFFNESS: f32 = 300.0;
+/// How quickly the suspension stops bouncing, per second.
+const SUSPENSION_DAMPING: f32 = 14.0;
+/// How much a change of vertical speed pushes the suspension.
+const SUSPENSION_KICK: f32 = 0.4;
+/// How far the body may move up or down on its suspension.
+const SUSPENSION_TRAVEL: f32 = 0.25;
+/// Longest time the suspension is advanced by at once, to keep it stable
+/// when frames are slow.
+const MAX_SUSPENSION_STEP: f32 = 1.0 / 30.0;
+
+/// Where the wheels touch the ground, relative to the model. The bike leans
+/// around it.
+///
+/// This is the bottom of the tires of `models/chopper_motorbike.glb`: the
+/// `torus` meshes of its `front_wheel_66` and `rear_wheel_74` nodes have their
+/// centers 0.21875 above the model's origin and a radius of 0.78125.
+const GROUND_CONTACT: Vec3 = Vec3::new(0.0, 0.21875 - 0.78125, 0.0);
+
+/// How the model of a [`Bike`] moves on its own, apart from the bike itself.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default, Reflect)]
+#[reflect(Component)]
+pub struct BikeVisuals {
+    /// Roll of the body, in radians. It is positive when leaning left.
+    pub lean: f32,
+    /// How far the body is raised on its suspension.
+    pub suspension: f32,
+    suspension_speed: f32,
+    last_vertical_speed: f32,
+}
+
+/// The part of the player's model that leans and bobs.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BikeBody;
+
+/// A wheel of the model, spun around its axle from where the model put it.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+struct Wheel {
+    rest: Transform,
+    /// The shape of the wheel, once its meshes have loaded.
+    shape: Option<WheelShape>,
+    /// Rotation of the wheel, in radians.
+    angle: f32,
+}
+
+/// The shape of a [`Wheel`], relative to its node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct WheelShape {
+    center: Vec3,
+    radius: f32,
+}
+
+fn is_wheel(name: &Name) -> bool {
+    WHEEL_NODES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+/// Finds the wheels of the model as its scene is spawned.
+fn find_wheels(mut commands: Commands, nodes: Query<(Entity, &Name, &Transform), Added<Name>>) {
+    for (entity, name, transform) in &nodes {
+        if is_wheel(name) {
+            commands.entity(entity).insert(Wheel {
+                rest: *transform,
+                shape: None,
+                angle: 0.0,
+            });
+        }
+    }
+}
+
+/// Warns when the model of a bike has no wheels to spin.
+fn warn_about_missing_wheels(
+    mut ready: EventReader<SceneInstanceReady>,
+    bodies: Query<(), With<BikeBody>>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+) {
+    for &SceneInstanceReady { parent } in ready.read() {
+        if !bodies.contains(parent) {
+            continue;
+        }
+        let has_wheels = children
+            .iter_descendants(parent)
+            .filter_map(|entity| names.get(entity).ok())
+            .any(is_wheel);
+        if !has_wheels {
+            warn!("The bike model has no nodes named like {WHEEL_NODES:?} to spin as wheels");
+        }
+    }
+}
+
+/// Works out the center and radius of each wheel from the bounds of its meshes,
+/// once they have all loaded.
+fn measure_wheels(
+    mut wheels: Query<(Entity, &mut Wheel)>,
+    children: Query<&Children>,
+    parts: Query<(&Transform, Option<&Aabb>, Has<Handle<Mesh>>)>,
+) {
+    'wheels: for (entity, mut wheel) in &mut wheels {
+        if wheel.shape.is_some() {
+            continue;
+        }
+        let mut min = Vec3::INFINITY;
+        let mut max = Vec3::NEG_INFINITY;
+        // Transforms of the parts relative to the wheel.
+        let mut stack = vec![(entity, Affine3A::IDENTITY)];
+        while let Some((parent, to_wheel)) = stack.pop() {
+            for &child in children.get(parent).into_iter().flatten() {
+                let Ok((transform, aabb, has_mesh)) = parts.get(child) else {
+                    continue;
+                };
+                let to_wheel = to_wheel * transform.compute_affine();
+                match aabb {
+                    Some(aabb) => {
+                        let (low, high) = (aabb.min().into(), aabb.max().into());
+                        for corner in 0..8 {
+                            let corner = Vec3::select(
+                                BVec3::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0),
+                                high,
+                                low,
+                            );
+                            let corner = to_wheel.transform_point3(corner);
+                            min = min.min(corner);
+                            max = max.max(corner);
+                        }
+                    }
+                    // Its bounds are computed once its mesh has loaded.
+                    None if has_mesh => continue 'wheels,
+                    None => {}
+                }
+                stack.push((child, to_wheel));
+            }
+        }
+        if min.cmple(max).all() {
+            wheel.shape = Some(WheelShape {
+                center: (min + max) / 2.0,
+                radius: (max.y - min.y) / 2.0,
+            });
+        }
+    }
+}
+
+fn update_bike_visuals(
+    time: Res<Time>,
+    tuning: Res<BikeTuning>,
+    mut bikes: Query<(
+        &mut BikeVisuals,
+        &Bike,
+        &BikeInput,
+        Option<&VerticalVelocity>,
+    )>,
+) {
+    let delta = time.delta_seconds();
+    for (mut visuals, bike, &input, vertical) in &mut bikes {
+        // Lean as far as a real bike would to make the same turn.
+        let turn = bike.speed * bike.turn_rate(&tuning, input);
+        let lean = (turn / LEAN_TURN).atan().clamp(-MAX_LEAN, MAX_LEAN);
+        visuals.lean += (lean - visuals.lean) * (1.0 - (-LEAN_RESPONSE * delta).exp());
+
+        // Landing squashes the suspension, and falling stretches it.
+        let vertical_speed = vertical.map_or(0.0, |vertical| vertical.0);
+        let kick = vertical_speed - visuals.last_vertical_speed;
+        visuals.last_vertical_speed = vertical_speed;
+        visuals.suspension_speed -= kick * SUSPENSION_KICK;
+        let step = delta.min(MAX_SUSPENSION_STEP);
+        let force = -SUSPENSION_STIFFNESS * visuals.suspension
+            - SUSPENSION_DAMPING * visuals.suspension_speed;
+        visuals.suspension_speed += force * step;
+        visuals.suspension = (visuals.suspension + visuals.suspension_speed * step)
+            .clamp(-SUSPENSION_TRAVEL, SUSPENSION_TRAVEL);
+    }
+}
+
+fn place_bike_bodies(
+    bikes: Query<&BikeVisuals>,
+    parents: Query<&Parent>,
+    mut bodies: Query<(Entity, &mut Transform), With<BikeBody>>,
+) {
+    for (entity, mut transform) in &mut bodies {
+        let Some(visuals) = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| bikes.get(ancestor).ok())
+        else {
+            continue;
+        };
+        let mut body = Transform::from_translation(Vec3::Y * visuals.suspension);
+        body.rotate_around(GROUND_CONTACT, Quat::from_rotation_z(visuals.lean));
+        *transform = body;
+    }
+}
+
+/// Rolls the wheels over the ground without slipping.
+fn spin_wheels(
+    time: Res<Time>,
+    bikes: Query<&Bike>,
+    parents: Query<&Parent>,
+    mut wheels: Query<(Entity, &mut Wheel, &mut Transform)>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut wheel, mut transform) in &mut wheels {
+        let Some(shape) = wheel.shape else {
+            continue;
+        };
+        let Some(bike) = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| bikes.get(ancestor).ok())
+        else {
+            continue;
+        };
+        wheel.angle = (wheel.angle - bike.speed / shape.radius * delta).rem_euclid(TAU);
+        let mut spin = Transform::IDENTITY;
+        spin.rotate_around(shape.center, Quat::from_rotation_x(wheel.angle));
+        *transform = wheel.rest * spin;
+    }
+}
@@ -0,0 +1,43 @@
+//! Tints the ambient light with the colour of the biome the player is riding through.
+//! The biomes themselves are laid out by [`crate::mapgen::biome`].
+
+use bevy::prelude::*;
+
+use crate::mapgen::{biome::biomes_at, MapSeed, WFCRules};
+
+use super::{map_change::Kare, spawn::player::Player, GameSystem};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        update_ambient_light
+            .in_set(GameSystem::MapChange)
+            .run_if(resource_exists::<AmbientLight>),
+    );
+}
+
+/// Tints the ambient light with the colour of the biomes around the player.
+fn update_ambient_light(
+    player: Query<&Transform, With<Player>>,
+    kare: Res<Kare>,
+    rules: Res<WFCRules>,
+    seed: Res<MapSeed>,
+    mut ambient_light: ResMut<AmbientLight>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let biomes = rules.biomes();
+    if biomes.is_empty() || !kare.is_valid() {
+        return;
+    }
+
+    let cell = (player.translation.xz() / kare.kare_kenar_uzunlugu as f32)
+        .round()
+        .as_ivec2();
+    let [(a, _), (b, influence)] = biomes_at(*seed, cell, biomes.len());
+    let color = biomes[a].ambient.mix(&biomes[b].ambient, influence);
+    if ambient_light.color != color {
+        ambient_light.color = color;
+    }
+}
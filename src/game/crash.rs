@@ -0,0 +1,92 @@
+//! Detect when the player rides into an obstacle, and rumble the player's gamepad
+//! when it does.
+
+use bevy::{
+    input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest},
+    prelude::*,
+    utils::{Duration, HashSet},
+};
+use bevy_rapier3d::prelude::*;
+
+use super::{input::ActiveGamepad, map_change::Obstacle, spawn::player::Player, GameSystem};
+
+pub fn plugin(app: &mut App) {
+    app.add_systems(
+        FixedUpdate,
+        detect_crashes
+            .in_set(GameSystem::Movement)
+            .after(PhysicsSet::Writeback),
+    );
+    app.observe(rumble_on_crash);
+}
+
+/// How long a crash rumbles the gamepad.
+const RUMBLE_DURATION: Duration = Duration::from_millis(400);
+/// Speed of a crash that rumbles the gamepad as hard as it can.
+const FULL_RUMBLE_SPEED: f32 = 40.0;
+
+/// Triggered when the player runs into an [`Obstacle`].
+/// Observe it to react to crashes.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct Crash {
+    /// The collider the player ran into.
+    pub obstacle: Entity,
+    /// How fast the player was moving across the ground when it hit the obstacle.
+    pub speed: f32,
+}
+
+/// Triggers a [`Crash`] when the player touches an obstacle it was not touching
+/// during its previous move, so sliding along a wall only crashes once.
+fn detect_crashes(
+    mut commands: Commands,
+    time: Res<Time>,
+    player: Query<Ref<KinematicCharacterControllerOutput>, With<Player>>,
+    obstacles: Query<(), With<Obstacle>>,
+    mut touching: Local<HashSet<Entity>>,
+) {
+    let Ok(output) = player.get_single() else {
+        touching.clear();
+        return;
+    };
+    // The output is only updated when the player moves.
+    if !output.is_changed() {
+        return;
+    }
+
+    let hits: HashSet<Entity> = output
+        .collisions
+        .iter()
+        .map(|collision| collision.entity)
+        .filter(|&entity| obstacles.contains(entity))
+        .collect();
+    let delta = time.delta_seconds();
+    let speed = if delta > 0.0 {
+        output.desired_translation.xz().length() / delta
+    } else {
+        0.0
+    };
+    for &obstacle in hits.difference(&touching) {
+        commands.trigger(Crash { obstacle, speed });
+    }
+    *touching = hits;
+}
+
+/// Rumbles the [`ActiveGamepad`], harder the faster the crash.
+fn rumble_on_crash(
+    trigger: Trigger<Crash>,
+    active: Res<ActiveGamepad>,
+    mut rumble: EventWriter<GamepadRumbleRequest>,
+) {
+    let Some(gamepad) = active.0 else {
+        return;
+    };
+    let strength = (trigger.event().speed / FULL_RUMBLE_SPEED).clamp(0.2, 1.0);
+    rumble.send(GamepadRumbleRequest::Add {
+        gamepad,
+        duration: RUMBLE_DURATION,
+        intensity: GamepadRumbleIntensity {
+            strong_motor: strength,
+            weak_motor: strength.sqrt(),
+        },
+    });
+}
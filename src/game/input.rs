@@ -0,0 +1,524 @@
+//! Actions the player can take, and the keys and gamepad inputs bound to them.
+//!
+//! Gameplay reads the [`ActionState`] rather than the devices, so that the inputs
+//! can be rebound in the settings screen. Gamepad triggers and sticks hold their
+//! actions partway, for analog throttle and steering. The [`Bindings`] are saved to their
+//! [`BindingsFile`] whenever they change, and loaded from it when the game starts.
+//! A file that cannot be used is ignored in favour of the default bindings.
+
+use bevy::{
+    ecs::system::SystemParam,
+    input::{
+        gamepad::{GamepadAxisChangedEvent, GamepadButtonChangedEvent, GamepadConnectionEvent},
+        InputSystem,
+    },
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, path::PathBuf};
+use thiserror::Error;
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<Bindings>()
+        .init_resource::<BindingsFile>()
+        .init_resource::<ActionState>()
+        .init_resource::<Rebinding>()
+        .init_resource::<ActiveGamepad>()
+        .add_systems(Startup, load_bindings)
+        .add_systems(
+            PreUpdate,
+            (
+                track_active_gamepad,
+                update_action_state,
+                capture_rebinding.run_if(|rebinding: Res<Rebinding>| rebinding.0.is_some()),
+            )
+                .chain()
+                .after(InputSystem),
+        )
+        .add_systems(
+            Update,
+            save_bindings
+                .run_if(resource_changed::<Bindings>.and_then(not(resource_added::<Bindings>))),
+        );
+}
+
+/// Where the bindings are saved, relative to the working directory.
+pub const BINDINGS_PATH: &str = "settings/bindings.ron";
+
+/// Something the player can do.
+#[derive(
+    Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize, Reflect,
+)]
+pub enum Action {
+    Throttle,
+    Brake,
+    SteerLeft,
+    SteerRight,
+    Boost,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 6] = [
+        Action::Throttle,
+        Action::Brake,
+        Action::SteerLeft,
+        Action::SteerRight,
+        Action::Boost,
+        Action::Pause,
+    ];
+
+    /// The name shown to the player.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Throttle => "Throttle",
+            Action::Brake => "Brake",
+            Action::SteerLeft => "Steer Left",
+            Action::SteerRight => "Steer Right",
+            Action::Boost => "Boost",
+            Action::Pause => "Pause",
+        }
+    }
+
+    /// Returns `true` if the action must keep a binding on every device it has
+    /// one on, so the player can always get back to the menus.
+    pub fn is_required(self) -> bool {
+        matches!(self, Action::Pause)
+    }
+}
+
+/// An input that can trigger an [`Action`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    /// A button of any gamepad. Triggers are held as far as they are pulled.
+    GamepadButton(GamepadButtonType),
+    /// A stick of any gamepad, held as far as it is pushed towards the positive
+    /// end of `axis`, or its negative end if `negative`.
+    GamepadAxis {
+        axis: GamepadAxisType,
+        negative: bool,
+    },
+}
+
+impl Binding {
+    /// Inputs that cancel rebinding instead of being bound.
+    pub const CANCEL: [Binding; 2] = [
+        Binding::Key(KeyCode::Escape),
+        Binding::GamepadButton(GamepadButtonType::East),
+    ];
+
+    /// Returns `true` if both bindings come from the same kind of device.
+    fn same_device(self, other: Binding) -> bool {
+        matches!(
+            (self, other),
+            (Binding::Key(_), Binding::Key(_))
+                | (
+                    Binding::GamepadButton(_) | Binding::GamepadAxis { .. },
+                    Binding::GamepadButton(_) | Binding::GamepadAxis { .. },
+                )
+        )
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Binding::Key(key) => {
+                let name = format!("{key:?}");
+                let name = name
+                    .strip_prefix("Key")
+                    .or_else(|| name.strip_prefix("Digit"))
+                    .unwrap_or(&name);
+                f.write_str(name)
+            }
+            Binding::GamepadButton(button) => match button {
+                GamepadButtonType::South => f.write_str("A"),
+                GamepadButtonType::East => f.write_str("B"),
+                GamepadButtonType::North => f.write_str("Y"),
+                GamepadButtonType::West => f.write_str("X"),
+                GamepadButtonType::LeftTrigger => f.write_str("LB"),
+                GamepadButtonType::LeftTrigger2 => f.write_str("LT"),
+                GamepadButtonType::RightTrigger => f.write_str("RB"),
+                GamepadButtonType::RightTrigger2 => f.write_str("RT"),
+                GamepadButtonType::LeftThumb => f.write_str("LS"),
+                GamepadButtonType::RightThumb => f.write_str("RS"),
+                GamepadButtonType::DPadUp => f.write_str("D-Pad Up"),
+                GamepadButtonType::DPadDown => f.write_str("D-Pad Down"),
+                GamepadButtonType::DPadLeft => f.write_str("D-Pad Left"),
+                GamepadButtonType::DPadRight => f.write_str("D-Pad Right"),
+                button => write!(f, "{button:?}"),
+            },
+            Binding::GamepadAxis { axis, negative } => {
+                let (stick, towards) = match axis {
+                    GamepadAxisType::LeftStickX => ("Left Stick", ["Right", "Left"]),
+                    GamepadAxisType::LeftStickY => ("Left Stick", ["Up", "Down"]),
+                    GamepadAxisType::RightStickX => ("Right Stick", ["Right", "Left"]),
+                    GamepadAxisType::RightStickY => ("Right Stick", ["Up", "Down"]),
+                    axis => return write!(f, "{axis:?} {}", if negative { "-" } else { "+" }),
+                };
+                write!(f, "{stick} {}", towards[usize::from(negative)])
+            }
+        }
+    }
+}
+
+/// The devices [`Binding`]s are read from.
+#[derive(SystemParam)]
+pub struct InputDevices<'w> {
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_buttons: Res<'w, ButtonInput<GamepadButton>>,
+    button_axes: Res<'w, Axis<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl InputDevices<'_> {
+    /// How far `binding` is held on any device, from 0 to 1.
+    pub fn value(&self, binding: Binding) -> f32 {
+        match binding {
+            Binding::Key(key) => {
+                if self.keys.pressed(key) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Binding::GamepadButton(button) => self
+                .gamepads
+                .iter()
+                .map(|gamepad| {
+                    let button = GamepadButton::new(gamepad, button);
+                    self.button_axes.get(button).unwrap_or_else(|| {
+                        if self.gamepad_buttons.pressed(button) {
+                            1.0
+                        } else {
+                            0.0
+                        }
+                    })
+                })
+                .fold(0.0, f32::max),
+            Binding::GamepadAxis { axis, negative } => self
+                .gamepads
+                .iter()
+                .filter_map(|gamepad| self.axes.get(GamepadAxis::new(gamepad, axis)))
+                .map(|value| if negative { -value } else { value })
+                .fold(0.0, f32::max),
+        }
+    }
+
+    /// The key or gamepad button pressed this frame, or else a stick pushed most
+    /// of the way, if any.
+    pub fn just_pressed(&self) -> Option<Binding> {
+        const STICKS: [GamepadAxisType; 4] = [
+            GamepadAxisType::LeftStickX,
+            GamepadAxisType::LeftStickY,
+            GamepadAxisType::RightStickX,
+            GamepadAxisType::RightStickY,
+        ];
+        if let Some(&key) = self.keys.get_just_pressed().next() {
+            return Some(Binding::Key(key));
+        }
+        if let Some(button) = self.gamepad_buttons.get_just_pressed().next() {
+            return Some(Binding::GamepadButton(button.button_type));
+        }
+        STICKS
+            .into_iter()
+            .flat_map(|axis| [false, true].map(|negative| Binding::GamepadAxis { axis, negative }))
+            .find(|&binding| self.value(binding) > 0.5)
+    }
+}
+
+/// The inputs bound to every [`Action`].
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bindings(BTreeMap<Action, Vec<Binding>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::{GamepadButton as Button, Key};
+        let stick = |negative| Binding::GamepadAxis {
+            axis: GamepadAxisType::LeftStickX,
+            negative,
+        };
+        Self(BTreeMap::from([
+            (
+                Action::Throttle,
+                vec![
+                    Key(KeyCode::KeyW),
+                    Key(KeyCode::ArrowUp),
+                    Button(GamepadButtonType::RightTrigger2),
+                ],
+            ),
+            (
+                Action::Brake,
+                vec![
+                    Key(KeyCode::KeyS),
+                    Key(KeyCode::ArrowDown),
+                    Button(GamepadButtonType::LeftTrigger2),
+                ],
+            ),
+            (
+                Action::SteerLeft,
+                vec![Key(KeyCode::KeyA), Key(KeyCode::ArrowLeft), stick(true)],
+            ),
+            (
+                Action::SteerRight,
+                vec![Key(KeyCode::KeyD), Key(KeyCode::ArrowRight), stick(false)],
+            ),
+            (
+                Action::Boost,
+                vec![Key(KeyCode::ShiftLeft), Button(GamepadButtonType::West)],
+            ),
+            (
+                Action::Pause,
+                vec![Key(KeyCode::Escape), Button(GamepadButtonType::Start)],
+            ),
+        ]))
+    }
+}
+
+impl Bindings {
+    /// Every input bound to `action`.
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Binds `binding` to `action`, in place of the inputs of the same device that
+    /// were bound to it. Other actions lose `binding`, so one input never
+    /// triggers two actions.
+    ///
+    /// Nothing changes if that would leave a [required](Action::is_required) action
+    /// with no binding on the device of `binding`.
+    pub fn rebind(&mut self, action: Action, binding: Binding) -> Result<(), RebindError> {
+        let unbound = Action::ALL.into_iter().find(|&other| {
+            let bindings = self.get(other);
+            other != action
+                && other.is_required()
+                && bindings.contains(&binding)
+                && !bindings
+                    .iter()
+                    .any(|&bound| bound != binding && bound.same_device(binding))
+        });
+        if let Some(unbound) = unbound {
+            return Err(RebindError(unbound));
+        }
+        for bindings in self.0.values_mut() {
+            bindings.retain(|&bound| bound != binding);
+        }
+        let bindings = self.0.entry(action).or_default();
+        bindings.retain(|bound| !bound.same_device(binding));
+        bindings.push(binding);
+        Ok(())
+    }
+
+    /// Reads bindings saved with [`Bindings::save`]. Actions missing from the file
+    /// keep their default bindings.
+    ///
+    /// Fails if an input would trigger two actions, or a [required](Action::is_required)
+    /// action would have no binding.
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, BindingsError> {
+        let bytes = std::fs::read(path)?;
+        let loaded: Bindings = ron::de::from_bytes(&bytes)?;
+        let mut bindings = Bindings::default();
+        bindings.0.extend(loaded.0);
+        bindings.validate()?;
+        Ok(bindings)
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), BindingsError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), BindingsError> {
+        let mut bound = HashSet::new();
+        for &binding in self.0.values().flatten() {
+            if !bound.insert(binding) {
+                return Err(BindingsError::Duplicate(binding));
+            }
+        }
+        match Action::ALL
+            .into_iter()
+            .find(|&action| action.is_required() && self.get(action).is_empty())
+        {
+            Some(action) => Err(BindingsError::Unbound(action)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BindingsError {
+    #[error("could not access bindings: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse bindings: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("could not write bindings: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("{0} is bound more than once")]
+    Duplicate(Binding),
+    #[error("{} has no binding", .0.name())]
+    Unbound(Action),
+}
+
+/// A rebinding that would have left the action with no binding on its device.
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+#[error("{} would have no binding left", .0.name())]
+pub struct RebindError(pub Action);
+
+/// Where the [`Bindings`] are kept between runs, if anywhere.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct BindingsFile(pub Option<PathBuf>);
+
+impl Default for BindingsFile {
+    fn default() -> Self {
+        // The web has no file system to keep them in.
+        Self((!cfg!(target_family = "wasm")).then(|| PathBuf::from(BINDINGS_PATH)))
+    }
+}
+
+/// How far every [`Action`] is held this frame.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct ActionState {
+    values: HashMap<Action, f32>,
+    just_pressed: Vec<Action>,
+}
+
+impl ActionState {
+    /// How far `action` is held, from 0 to 1.
+    pub fn value(&self, action: Action) -> f32 {
+        self.values.get(&action).copied().unwrap_or_default()
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.value(action) > 0.5
+    }
+
+    /// Returns `true` if `action` started being held this frame.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+}
+
+/// The gamepad the player last used, if any. Bindings are read from every
+/// gamepad, but only this one is rumbled.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ActiveGamepad(pub Option<Gamepad>);
+
+/// The action waiting for the next input pressed to be bound to it, if any.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rebinding(pub Option<Action>);
+
+/// A run condition that is `true` when `action` started being held this frame.
+pub fn action_just_pressed(action: Action) -> impl Fn(Res<ActionState>) -> bool {
+    move |actions: Res<ActionState>| actions.just_pressed(action)
+}
+
+fn update_action_state(
+    bindings: Res<Bindings>,
+    devices: InputDevices,
+    mut actions: ResMut<ActionState>,
+) {
+    let actions = &mut *actions;
+    actions.just_pressed.clear();
+    for action in Action::ALL {
+        let value = bindings
+            .get(action)
+            .iter()
+            .map(|&binding| devices.value(binding))
+            .fold(0.0, f32::max);
+        let was_pressed = actions.value(action) > 0.5;
+        if value > 0.5 && !was_pressed {
+            actions.just_pressed.push(action);
+        }
+        actions.values.insert(action, value);
+    }
+}
+
+/// Makes the gamepad that last pressed a button or pushed a stick the
+/// [`ActiveGamepad`]. A gamepad connected while there is none becomes active too.
+fn track_active_gamepad(
+    mut connections: EventReader<GamepadConnectionEvent>,
+    mut buttons: EventReader<GamepadButtonChangedEvent>,
+    mut axes: EventReader<GamepadAxisChangedEvent>,
+    mut active: ResMut<ActiveGamepad>,
+) {
+    for connection in connections.read() {
+        if connection.disconnected() && active.0 == Some(connection.gamepad) {
+            active.0 = None;
+        } else if connection.connected() && active.0.is_none() {
+            active.0 = Some(connection.gamepad);
+        }
+    }
+    let used = buttons
+        .read()
+        .filter(|button| button.value > 0.5)
+        .map(|button| button.gamepad)
+        .chain(
+            axes.read()
+                .filter(|axis| axis.value.abs() > 0.5)
+                .map(|axis| axis.gamepad),
+        )
+        .last();
+    if used.is_some() {
+        active.0 = used;
+    }
+}
+
+/// Binds the next input pressed to the action waiting for one, or stops waiting
+/// if it is one of [`Binding::CANCEL`].
+fn capture_rebinding(
+    devices: InputDevices,
+    mut bindings: ResMut<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    // The gamepad button that started rebinding may still be held.
+    if rebinding.is_changed() {
+        return;
+    }
+    let Some(action) = rebinding.0 else {
+        return;
+    };
+    let Some(binding) = devices.just_pressed() else {
+        return;
+    };
+    if Binding::CANCEL.contains(&binding) {
+        rebinding.0 = None;
+        return;
+    }
+    // Keep waiting for another input if this one cannot be taken.
+    match bindings.rebind(action, binding) {
+        Ok(()) => rebinding.0 = None,
+        Err(error) => warn!("Cannot bind {binding} to {}: {error}", action.name()),
+    }
+}
+
+fn load_bindings(file: Res<BindingsFile>, mut bindings: ResMut<Bindings>) {
+    let Some(path) = &file.0 else {
+        return;
+    };
+    if !path.exists() {
+        return;
+    }
+    match Bindings::load(path) {
+        // They came from the file, so there is nothing new to save.
+        Ok(loaded) => *bindings.bypass_change_detection() = loaded,
+        Err(error) => warn!("Using the default bindings, {}: {error}", path.display()),
+    }
+}
+
+fn save_bindings(file: Res<BindingsFile>, bindings: Res<Bindings>) {
+    let Some(path) = &file.0 else {
+        return;
+    };
+    if let Err(error) = bindings.save(path) {
+        error!("Could not save the bindings to {}: {error}", path.display());
+    }
+}
@@ -0,0 +1,70 @@
+//! Smooths the motion of the player between simulation steps.
+//!
+//! The player moves in `FixedUpdate`, which may run more or less often than frames
+//! are drawn. Its model and the camera are drawn at a [`RenderPose`] between the
+//! last two steps instead, so the motion looks smooth at any frame rate.
+
+use bevy::prelude::*;
+
+use super::GameSystem;
+
+pub fn plugin(app: &mut App) {
+    app.register_type::<(PreviousPose, RenderPose)>();
+    app.add_systems(FixedFirst, record_previous_poses);
+    app.add_systems(
+        Update,
+        (interpolate_poses, place_smoothed_models)
+            .chain()
+            .in_set(GameSystem::Interpolation),
+    );
+}
+
+/// Where the entity was before the latest simulation step.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default, Reflect)]
+#[reflect(Component)]
+pub struct PreviousPose(pub Transform);
+
+/// Where the entity is drawn this frame, between its [`PreviousPose`] and its
+/// [`Transform`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default, Reflect)]
+#[reflect(Component)]
+pub struct RenderPose(pub Transform);
+
+/// A child drawn at the [`RenderPose`] of its parent, rather than where the
+/// parent is.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SmoothedModel;
+
+fn record_previous_poses(mut poses: Query<(&mut PreviousPose, &Transform)>) {
+    for (mut previous, transform) in &mut poses {
+        previous.0 = *transform;
+    }
+}
+
+fn interpolate_poses(
+    time: Res<Time<Fixed>>,
+    mut poses: Query<(&mut RenderPose, &PreviousPose, &Transform)>,
+) {
+    let t = time.overstep_fraction();
+    for (mut render, previous, transform) in &mut poses {
+        render.0 = Transform {
+            translation: previous.0.translation.lerp(transform.translation, t),
+            rotation: previous.0.rotation.slerp(transform.rotation, t),
+            scale: transform.scale,
+        };
+    }
+}
+
+fn place_smoothed_models(
+    parents: Query<(&RenderPose, &Transform)>,
+    mut models: Query<(&mut Transform, &Parent), (With<SmoothedModel>, Without<RenderPose>)>,
+) {
+    for (mut model, parent) in &mut models {
+        let Ok((render, transform)) = parents.get(parent.get()) else {
+            continue;
+        };
+        let inverse = transform.rotation.inverse();
+        model.translation = inverse * (render.0.translation - transform.translation);
+        model.rotation = inverse * render.0.rotation;
+    }
+}
@@ -0,0 +1,103 @@
+//! Level of detail: chunks far from the player are drawn with fewer details,
+//! and fog hides the edge of the map where chunks are unloaded.
+
+use bevy::prelude::*;
+
+use super::map_change::Kare;
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<TileLod>()
+        .add_systems(Update, update_fog);
+}
+
+/// How detailed a chunk is drawn.
+#[derive(Reflect, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum Lod {
+    /// Every tile is drawn with its full model.
+    #[default]
+    Near,
+    /// Floor tiles keep their model, and obstacles are drawn as simple shapes.
+    Mid,
+    /// Only the ground is drawn.
+    Far,
+}
+
+/// Where each [`Lod`] starts, as fractions of [`Kare::view_radius`].
+#[derive(Reflect, Resource, Clone, Copy, PartialEq, Debug)]
+#[reflect(Resource)]
+pub struct TileLod {
+    /// Chunks closer than this are drawn with [`Lod::Near`].
+    pub near: f32,
+    /// Chunks closer than this are drawn with [`Lod::Mid`], farther ones with [`Lod::Far`].
+    pub mid: f32,
+    /// How far past a boundary a chunk has to move before its detail changes,
+    /// so that chunks on a boundary do not keep switching back and forth.
+    pub hysteresis: f32,
+}
+
+impl Default for TileLod {
+    fn default() -> Self {
+        Self {
+            near: 0.45,
+            mid: 0.75,
+            hysteresis: 0.05,
+        }
+    }
+}
+
+impl TileLod {
+    /// The detail of a chunk `distance` away from the player, that is currently
+    /// drawn with `current`, or not drawn yet if `None`.
+    pub fn lod(&self, current: Option<Lod>, distance: f32, view_radius: f32) -> Lod {
+        let lod = |distance: f32| {
+            if distance < self.near * view_radius {
+                Lod::Near
+            } else if distance < self.mid * view_radius {
+                Lod::Mid
+            } else {
+                Lod::Far
+            }
+        };
+        let Some(current) = current else {
+            return lod(distance);
+        };
+        let margin = self.hysteresis * view_radius;
+        let coarser = lod(distance - margin);
+        let finer = lod(distance + margin);
+        if coarser > current {
+            coarser
+        } else if finer < current {
+            finer
+        } else {
+            current
+        }
+    }
+}
+
+/// Fades the world into the background colour between the start of [`Lod::Mid`]
+/// and the distance at which chunks are unloaded.
+fn update_fog(
+    mut commands: Commands,
+    kare: Res<Kare>,
+    lod: Res<TileLod>,
+    clear_color: Res<ClearColor>,
+    cameras: Query<(Entity, Has<FogSettings>), With<Camera3d>>,
+) {
+    if !kare.is_valid() {
+        return;
+    }
+    let changed = kare.is_changed() || lod.is_changed() || clear_color.is_changed();
+    let radius = kare.view_radius();
+    for (camera, has_fog) in &cameras {
+        if changed || !has_fog {
+            commands.entity(camera).insert(FogSettings {
+                color: clear_color.0,
+                falloff: FogFalloff::Linear {
+                    start: lod.near * radius,
+                    end: radius,
+                },
+                ..default()
+            });
+        }
+    }
+}
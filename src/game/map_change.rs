@@ -1,311 +1,814 @@
-use bevy::prelude::*;
+//! Streams the chunks of the map around the player: generates them on
+//! background tasks, spawns their tiles and despawns the ones left behind.
+//!
+//! Unlike movement, streaming runs in `Update` rather than `FixedUpdate`. It only
+//! reacts to where the player is, so running it once per drawn frame keeps the
+//! [`TILE_SPAWN_BUDGET`] per frame and never repeats it when a frame runs several
+//! fixed steps. Chunks spawned in a frame are there for the next fixed step, and
+//! they stream in well before the player can reach them.
+
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
 use bevy_inspector_egui::prelude::*;
-use rand::seq::SliceRandom;
-use std::collections::HashMap;
-use std::time::Duration;
-
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
+use thiserror::Error;
+
+use crate::mapgen::{
+    generate_chunk,
+    tileset::{TileCollider, Tileset},
+    ChunkFit, GeneratedChunk, MapSeed, WFCRules, CHUNK_SIZE,
+};
 use crate::screen::Screen;
-
-use super::{spawn::player::Player, GameSystem};
-
-pub(super) fn plugin(app: &mut App) {
+use crate::wfc::{self, Contradiction};
+
+use super::{
+    lod::{Lod, TileLod},
+    spawn::player::Player,
+    terrain::ChunkGround,
+    tile_mesh::{self, MeshMerger, TilePart, TileRendering},
+    tileset::{self, TilesetAsset, TilesetHandle},
+    GameSystem,
+};
+
+pub fn plugin(app: &mut App) {
     app.add_systems(
         Update,
         (
-            create_tile_map
-                .in_set(GameSystem::MapChange)
-                .run_if(run_if_empty_map),
-            update_tiles.in_set(GameSystem::MapChange),
-            destroy_tiles,
+            report_invalid_kare.run_if(resource_changed::<Kare>),
+            apply_tileset,
+            resolve_tile_meshes,
+            rebuild_changed_map,
+            update_chunks,
+            collect_generated_chunks,
+            spawn_generated_chunks,
+            update_chunk_lod,
         )
-            .chain(),
+            .chain()
+            .in_set(GameSystem::MapChange),
     )
+    .add_systems(OnExit(Screen::Playing), clear_loaded_chunks)
+    .register_type::<Chunk>()
+    .register_type::<Obstacle>()
     .init_resource::<Kare>()
+    .init_resource::<MapSeed>()
     .init_resource::<AssetData>()
-    .init_resource::<WFCRules>();
+    .init_resource::<TileRendering>()
+    .init_resource::<TileLod>()
+    .register_type::<TileRendering>()
+    .init_resource::<WFCRules>()
+    .init_resource::<LoadedChunks>()
+    .init_resource::<PendingChunks>()
+    .init_resource::<SpawnedTiles>();
 }
 
-const TIME: Duration = Duration::from_millis(500);
+/// How many tiles may be spawned in a single frame, counting both new chunks and
+/// chunks redrawn with another [`Lod`]. Chunks are spawned whole, so at least one
+/// new chunk is spawned per frame even if it has more tiles.
+pub const TILE_SPAWN_BUDGET: usize = 3 * CHUNK_SIZE * CHUNK_SIZE;
 
+/// The tiles spawned so far this frame, shared by [`spawn_generated_chunks`] and
+/// [`update_chunk_lod`] so that together they stay within [`TILE_SPAWN_BUDGET`].
+#[derive(Resource, Default)]
+struct SpawnedTiles(usize);
+
+/// A single tile of the map.
+/// With [`TileRendering::Merged`], floor tiles are part of their chunk's merged
+/// mesh and have no entity of their own. So are the tiles on slopes, which are bent
+/// to follow the ground, once their model has loaded.
 #[derive(Component, Debug, Clone, Copy, PartialEq, Default, Reflect)]
 pub struct Block {
+    /// The [`TileType::id`](crate::mapgen::TileType::id) of this tile.
     pub id: i32,
+    /// The global grid cell this tile occupies.
+    pub cell: IVec2,
+}
+
+/// A tile collider the bike crashes into, such as a wall or a tree.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[reflect(Component)]
+pub struct Obstacle;
+
+/// A square of `CHUNK_SIZE` x `CHUNK_SIZE` tiles that is spawned and despawned as a unit.
+/// Its tiles are spawned as children, standing on its [`ChunkGround`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct Chunk {
+    pub coord: IVec2,
+    /// How well the tiles of this chunk fit its neighbours.
+    pub fit: ChunkFit,
+    /// How detailed the chunk is currently drawn.
+    pub lod: Lod,
+}
+
+/// The tiles of a [`Chunk`], kept to redraw it when its [`Lod`] changes.
+#[derive(Component)]
+pub struct ChunkTiles {
+    pub grid: wfc::Grid,
+    /// The child entity holding everything that is drawn for the chunk.
+    visuals: Entity,
+}
+
+/// Colour of the ground of chunks drawn with [`Lod::Mid`] or [`Lod::Far`].
+const GROUND_COLOR: Color = Color::srgb(0.42, 0.45, 0.38);
+/// Colour of the simple shapes drawn for obstacles with [`Lod::Mid`].
+const PROXY_COLOR: Color = Color::srgb(0.55, 0.55, 0.52);
+
+/// How far below the tiles the ground of distant chunks is drawn, so it does not
+/// show through the floor tiles.
+const GROUND_OFFSET: f32 = -0.05;
+
+/// The chunk entities currently in the world, keyed by chunk coordinates.
+#[derive(Resource, Default)]
+pub struct LoadedChunks(HashMap<IVec2, Entity>);
+
+impl LoadedChunks {
+    pub fn get(&self, coord: IVec2) -> Option<Entity> {
+        self.0.get(&coord).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Chunks that came into view but have not been spawned yet.
+/// Their tiles are generated on the [`AsyncComputeTaskPool`].
+#[derive(Resource, Default)]
+pub struct PendingChunks {
+    tasks: HashMap<IVec2, Task<Result<GeneratedChunk, Contradiction>>>,
+    /// Generated chunks waiting to be spawned, in the order they finished.
+    generated: VecDeque<(IVec2, GeneratedChunk)>,
 }
 
-#[derive(Reflect, Resource, Default, InspectorOptions)]
+impl PendingChunks {
+    /// Number of chunks that are still being generated or waiting to be spawned.
+    pub fn len(&self) -> usize {
+        self.tasks.len() + self.generated.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn contains(&self, coord: IVec2) -> bool {
+        self.tasks.contains_key(&coord) || self.generated.iter().any(|(c, _)| *c == coord)
+    }
+
+    /// Forgets every chunk that is not in `visible`. Dropping a task cancels it.
+    fn retain(&mut self, visible: &HashSet<IVec2>) {
+        self.tasks.retain(|coord, _| visible.contains(coord));
+        self.generated.retain(|(coord, _)| visible.contains(coord));
+    }
+
+    fn clear(&mut self) {
+        self.tasks.clear();
+        self.generated.clear();
+    }
+}
+
+/// Size of the map around the player.
+///
+/// The fields can be edited at runtime, for example in the inspector, so they are
+/// not trusted: systems check [`Kare::validate`] and leave the map alone while the
+/// settings are invalid.
+#[derive(Reflect, Resource, Clone, Copy, PartialEq, Eq, Debug, InspectorOptions)]
 #[reflect(Resource, InspectorOptions)]
 pub struct Kare {
+    /// Number of tiles in a row of the visible map, so the view is this many tiles across.
+    #[inspector(min = Kare::MIN_TILES_IN_VIEW, max = Kare::MAX_TILES_IN_VIEW)]
     pub bir_siradaki_kare_sayisi: usize,
+    /// Length of a side of a tile, in world units.
+    #[inspector(min = Kare::MIN_TILE_LENGTH, max = Kare::MAX_TILE_LENGTH)]
     pub kare_kenar_uzunlugu: usize,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-pub enum TileType {
-    Wall,
-    Tree,
-    Column,
-    Ground,
+impl Default for Kare {
+    fn default() -> Self {
+        Self {
+            bir_siradaki_kare_sayisi: 40,
+            kare_kenar_uzunlugu: 4,
+        }
+    }
 }
 
-#[derive(Resource)]
-pub struct WFCRules {
-    allowed_neighbors: HashMap<TileType, Vec<TileType>>,
-}
+impl Kare {
+    pub const MIN_TILES_IN_VIEW: usize = 1;
+    /// Larger views load more chunks than can be generated in time.
+    pub const MAX_TILES_IN_VIEW: usize = 256;
+    pub const MIN_TILE_LENGTH: usize = 1;
+    pub const MAX_TILE_LENGTH: usize = 64;
+
+    /// Creates settings that are known to be valid.
+    pub fn new(tiles_in_view: usize, tile_length: usize) -> Result<Self, KareError> {
+        let kare = Self {
+            bir_siradaki_kare_sayisi: tiles_in_view,
+            kare_kenar_uzunlugu: tile_length,
+        };
+        kare.validate()?;
+        Ok(kare)
+    }
 
-impl Default for WFCRules {
-    fn default() -> Self {
-        let mut allowed_neighbors = HashMap::new();
-        allowed_neighbors.insert(
-            TileType::Ground,
-            vec![
-                TileType::Ground,
-                TileType::Wall,
-                TileType::Tree,
-                TileType::Column,
-            ],
-        );
-        allowed_neighbors.insert(TileType::Wall, vec![TileType::Ground, TileType::Wall]);
-        allowed_neighbors.insert(TileType::Tree, vec![TileType::Ground]);
-        allowed_neighbors.insert(TileType::Column, vec![TileType::Ground]);
-        Self { allowed_neighbors }
+    /// Checks that every field is within its range.
+    pub fn validate(&self) -> Result<(), KareError> {
+        let tiles_in_view = Self::MIN_TILES_IN_VIEW..=Self::MAX_TILES_IN_VIEW;
+        if !tiles_in_view.contains(&self.bir_siradaki_kare_sayisi) {
+            return Err(KareError::TilesInView(self.bir_siradaki_kare_sayisi));
+        }
+        let tile_length = Self::MIN_TILE_LENGTH..=Self::MAX_TILE_LENGTH;
+        if !tile_length.contains(&self.kare_kenar_uzunlugu) {
+            return Err(KareError::TileLength(self.kare_kenar_uzunlugu));
+        }
+        Ok(())
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Chunks closer to the player than this are kept loaded.
+    pub fn view_radius(&self) -> f32 {
+        self.bir_siradaki_kare_sayisi as f32 * self.kare_kenar_uzunlugu as f32 / 2.0
     }
 }
 
-#[derive(Resource)]
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum KareError {
+    #[error(
+        "the view must be between {} and {} tiles across, got {0}",
+        Kare::MIN_TILES_IN_VIEW,
+        Kare::MAX_TILES_IN_VIEW
+    )]
+    TilesInView(usize),
+    #[error(
+        "tiles must be between {} and {} units long, got {0}",
+        Kare::MIN_TILE_LENGTH,
+        Kare::MAX_TILE_LENGTH
+    )]
+    TileLength(usize),
+}
+
+/// The scene spawned for each tile of the loaded [`Tileset`], and the meshes
+/// drawn in its place far from the player.
+#[derive(Resource, Default)]
 pub struct AssetData {
-    assets: HashMap<TileType, String>,
+    models: Vec<TileModel>,
+    ground_material: Handle<StandardMaterial>,
+    proxy_material: Handle<StandardMaterial>,
 }
 
-impl Default for AssetData {
-    fn default() -> Self {
-        let mut assets = HashMap::new();
-        assets.insert(TileType::Wall, "models/wall.glb#Scene0".to_string());
-        assets.insert(TileType::Tree, "models/tree.glb#Scene0".to_string());
-        assets.insert(TileType::Column, "models/column.glb#Scene0".to_string());
-        assets.insert(TileType::Ground, "models/floor.glb#Scene0".to_string());
-        Self { assets }
+struct TileModel {
+    scene: Handle<Scene>,
+    scale: Vec3,
+    rotation: Quat,
+    collider: Option<TileCollider>,
+    /// Floor tiles are merged into a single mesh with [`TileRendering::Merged`].
+    floor: bool,
+    /// The meshes of the scene, once it has loaded. Used with [`TileRendering::Merged`],
+    /// and to bend the tiles on slopes.
+    parts: Option<Vec<TilePart>>,
+    /// Drawn instead of the scene with [`Lod::Mid`].
+    proxy: Option<Handle<Mesh>>,
+}
+
+impl AssetData {
+    pub fn from_tileset(
+        tileset: &Tileset,
+        asset_server: &AssetServer,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> Self {
+        // Tiles share the proxy of their definition across rotations.
+        let mut proxies = HashMap::new();
+        let models = tileset
+            .variants()
+            .iter()
+            .map(|variant| {
+                let definition = variant.definition;
+                let proxy = proxies
+                    .entry(definition.id.as_str())
+                    .or_insert_with(|| {
+                        let mesh = tileset::proxy_mesh(definition.collider.as_ref()?)?;
+                        Some(meshes.add(mesh))
+                    })
+                    .clone();
+                TileModel {
+                    scene: asset_server.load(&definition.model),
+                    scale: Vec3::from_array(definition.scale),
+                    rotation: variant.rotation(),
+                    collider: definition.collider.clone(),
+                    floor: definition.drivable,
+                    parts: None,
+                    proxy,
+                }
+            })
+            .collect();
+        Self {
+            models,
+            ground_material: materials.add(StandardMaterial {
+                base_color: GROUND_COLOR,
+                perceptual_roughness: 1.0,
+                ..default()
+            }),
+            proxy_material: materials.add(StandardMaterial {
+                base_color: PROXY_COLOR,
+                perceptual_roughness: 1.0,
+                ..default()
+            }),
+        }
+    }
+
+    /// Returns `true` once every tile model has finished loading.
+    pub fn is_loaded(&self, asset_server: &AssetServer) -> bool {
+        self.models
+            .iter()
+            .all(|model| asset_server.is_loaded_with_dependencies(&model.scene))
     }
 }
 
-fn create_tile_map(
-    mut commands: Commands,
-    kare: Res<Kare>,
-    asset_data: Res<AssetData>,
-    player: Query<&Transform, With<Player>>,
+/// Rebuilds the rules and models whenever the tileset finishes loading or is edited.
+/// [`rebuild_changed_map`] then regenerates the loaded chunks with the new tiles.
+fn apply_tileset(
+    mut events: EventReader<AssetEvent<TilesetAsset>>,
+    handle: Res<TilesetHandle>,
+    tilesets: Res<Assets<TilesetAsset>>,
     asset_server: Res<AssetServer>,
-    rules: Res<WFCRules>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut rules: ResMut<WFCRules>,
+    mut asset_data: ResMut<AssetData>,
 ) {
-    let Ok(player) = player.get_single() else {
+    let changed = events
+        .read()
+        .any(|event| event.is_loaded_with_dependencies(&handle.0) || event.is_modified(&handle.0));
+    if !changed {
+        return;
+    }
+    let Some(tileset) = tilesets.get(&handle.0) else {
         return;
     };
 
-    let grid_size = kare.bir_siradaki_kare_sayisi;
-    let mut grid = vec![vec![None; grid_size]; grid_size];
-
-    for x in 0..grid_size {
-        for z in 0..grid_size {
-            let neighbors = [
-                if x > 0 { grid[x - 1][z] } else { None },
-                if z > 0 { grid[x][z - 1] } else { None },
-                if x < grid_size - 1 {
-                    grid[x + 1][z]
-                } else {
-                    None
-                },
-                if z < grid_size - 1 {
-                    grid[x][z + 1]
-                } else {
-                    None
-                },
-            ];
-
-            let tile_type = wfc_select_tile(&rules, &neighbors);
-            grid[x][z] = Some(tile_type);
-
-            let (world_x, world_z) = grid_to_world(x as f32, z as f32, kare.kare_kenar_uzunlugu);
-            let (location_x, location_y, location_z) =
-                calculate_tile_position(player, world_x, world_z, &kare);
-
-            let asset = asset_data.assets.get(&tile_type).unwrap();
-
-            commands.spawn((
-                Name::new("Tile"),
-                SceneBundle {
-                    scene: asset_server.load(asset),
-                    transform: Transform::from_xyz(location_x, location_y, location_z).with_scale(
-                        Vec3::new(
-                            kare.kare_kenar_uzunlugu as f32 / 4.0,
-                            10.0,
-                            kare.kare_kenar_uzunlugu as f32 / 4.0,
-                        ),
-                    ),
-                    ..default()
-                },
-                StateScoped(Screen::Playing),
-                Block { id: 1 },
-            ));
+    info!("Applying tileset with {} tiles", tileset.tiles.len());
+    *rules = WFCRules::from_tileset(tileset);
+    *asset_data = AssetData::from_tileset(tileset, &asset_server, &mut meshes, &mut materials);
+}
+
+/// Looks up the meshes of the tile models as they finish loading.
+fn resolve_tile_meshes(
+    scenes: Res<Assets<Scene>>,
+    asset_server: Res<AssetServer>,
+    mut asset_data: ResMut<AssetData>,
+) {
+    let resolvable = |model: &TileModel| {
+        model.parts.is_none() && asset_server.is_loaded_with_dependencies(&model.scene)
+    };
+    if !asset_data.models.iter().any(resolvable) {
+        return;
+    }
+    for model in &mut asset_data.models {
+        if resolvable(model) {
+            model.parts = scenes.get(&model.scene).map(tile_mesh::scene_parts);
         }
     }
 }
 
-fn update_tiles(
+/// Despawns the whole map when the rules, the seed, the tile length or the way tiles
+/// are drawn change, so that [`update_chunks`] rebuilds it around the player with the
+/// new settings. Tiles drawn from their scene while their model was still loading are
+/// also rebuilt once it has loaded.
+/// Changing only the size of the view keeps the chunks that stay in view.
+fn rebuild_changed_map(
     mut commands: Commands,
     kare: Res<Kare>,
-    asset_data: Res<AssetData>,
-    player: Query<&Transform, With<Player>>,
-    asset_server: Res<AssetServer>,
+    seed: Res<MapSeed>,
+    rendering: Res<TileRendering>,
     rules: Res<WFCRules>,
-    mut last_update: Local<Duration>,
-    time: Res<Time>,
-    input: Res<ButtonInput<KeyCode>>,
+    asset_data: Res<AssetData>,
+    mut loaded: ResMut<LoadedChunks>,
+    mut pending: ResMut<PendingChunks>,
+    mut built_with: Local<Option<(MapSeed, usize, TileRendering)>>,
 ) {
-    if !input.any_pressed([
-        KeyCode::KeyW,
-        KeyCode::ArrowUp,
-        KeyCode::KeyS,
-        KeyCode::ArrowDown,
-        KeyCode::KeyA,
-        KeyCode::ArrowLeft,
-        KeyCode::KeyD,
-        KeyCode::ArrowRight,
-    ]) {
+    // The inspector may mark settings as changed without editing them, so compare values.
+    let settings = (*seed, kare.kare_kenar_uzunlugu, *rendering);
+    let changed = built_with
+        .replace(settings)
+        .is_some_and(|previous| previous != settings);
+    if !changed && !rules.is_changed() && !asset_data.is_changed() {
         return;
     }
-
-    let now = time.elapsed();
-    if *last_update + Duration::from_millis(500) > now {
+    if loaded.is_empty() && pending.is_empty() {
         return;
     }
-    *last_update = now;
 
+    info!("Map settings changed, rebuilding the map");
+    for (_, entity) in loaded.0.drain() {
+        commands.entity(entity).despawn_recursive();
+    }
+    pending.clear();
+}
+
+/// Logs why the map stopped updating when [`Kare`] is changed to invalid values.
+fn report_invalid_kare(kare: Res<Kare>) {
+    if let Err(error) = kare.validate() {
+        error!("Invalid map settings, the map is not updated until they are fixed: {error}");
+    }
+}
+
+/// Starts generating the chunks that came into view and despawns the ones that left it.
+/// A chunk is always generated from the same seed, so revisited areas look the same.
+fn update_chunks(
+    mut commands: Commands,
+    kare: Res<Kare>,
+    player: Query<&Transform, With<Player>>,
+    rules: Res<WFCRules>,
+    mut shared_rules: Local<Option<Arc<WFCRules>>>,
+    seed: Res<MapSeed>,
+    mut loaded: ResMut<LoadedChunks>,
+    mut pending: ResMut<PendingChunks>,
+) {
+    // The generation tasks share a copy of the rules that is only remade when they change.
+    if rules.is_changed() {
+        *shared_rules = None;
+    }
     let Ok(player) = player.get_single() else {
         return;
     };
+    if !kare.is_valid() || rules.tile_count() == 0 {
+        return;
+    }
 
-    let grid_size = kare.bir_siradaki_kare_sayisi;
-    let mut grid = vec![vec![None; grid_size]; grid_size];
-
-    for x in 0..grid_size {
-        for z in 0..grid_size {
-            let (world_x, world_z) = grid_to_world(x as f32, z as f32, kare.kare_kenar_uzunlugu);
-            let (location_x, location_y, location_z) =
-                calculate_tile_position(player, world_x, world_z, &kare);
-
-            if is_tile_in_range(player, location_x, location_z, &kare) {
-                let neighbors = [
-                    if x > 0 { grid[x - 1][z] } else { None },
-                    if z > 0 { grid[x][z - 1] } else { None },
-                    if x < grid_size - 1 {
-                        grid[x + 1][z]
-                    } else {
-                        None
-                    },
-                    if z < grid_size - 1 {
-                        grid[x][z + 1]
-                    } else {
-                        None
-                    },
-                ];
-
-                let tile_type = wfc_select_tile(&rules, &neighbors);
-                grid[x][z] = Some(tile_type);
-
-                let asset = asset_data.assets.get(&tile_type).unwrap();
-
-                commands.spawn((
-                    Name::new("Tile"),
-                    SceneBundle {
-                        scene: asset_server.load(asset),
-                        transform: Transform::from_xyz(location_x, location_y, location_z)
-                            .with_scale(Vec3::new(
-                                kare.kare_kenar_uzunlugu as f32 / 4.0,
-                                10.0,
-                                kare.kare_kenar_uzunlugu as f32 / 4.0,
-                            )),
-                        ..default()
-                    },
-                    StateScoped(Screen::Playing),
-                    Block { id: 1 },
-                ));
-            }
+    let visible = chunks_in_view(player.translation, &kare);
+    loaded.0.retain(|coord, &mut entity| {
+        let keep = visible.contains(coord);
+        if !keep {
+            commands.entity(entity).despawn_recursive();
+        }
+        keep
+    });
+    pending.retain(&visible);
+
+    let shared_rules = shared_rules.get_or_insert_with(|| Arc::new(rules.clone()));
+    for coord in visible {
+        if loaded.0.contains_key(&coord) || pending.contains(coord) {
+            continue;
         }
+        let rules = Arc::clone(shared_rules);
+        let seed = *seed;
+        let task =
+            AsyncComputeTaskPool::get().spawn(async move { generate_chunk(&rules, seed, coord) });
+        pending.tasks.insert(coord, task);
     }
 }
 
-fn destroy_tiles(
+/// Moves the chunks whose generation has finished to the spawn queue.
+fn collect_generated_chunks(mut pending: ResMut<PendingChunks>) {
+    let mut finished = Vec::new();
+    pending.tasks.retain(|&coord, task| {
+        let Some(result) = block_on(future::poll_once(task)) else {
+            return true;
+        };
+        finished.push((coord, result));
+        false
+    });
+
+    for (coord, result) in finished {
+        match result {
+            Ok(chunk) => pending.generated.push_back((coord, chunk)),
+            Err(error) => warn!("Could not generate chunk {coord}: {error}"),
+        }
+    }
+}
+
+/// Spawns generated chunks, but no more than [`TILE_SPAWN_BUDGET`] tiles per frame
+/// so that large batches of chunks do not stall a single frame.
+fn spawn_generated_chunks(
     mut commands: Commands,
-    kare: Res<Kare>,
+    tile_lod: Res<TileLod>,
     player: Query<&Transform, With<Player>>,
-    block: Query<(Entity, &Transform), With<Block>>,
-    mut last_sfx: Local<Duration>,
-    time: Res<Time>,
+    mut painter: ChunkPainter,
+    mut loaded: ResMut<LoadedChunks>,
+    mut pending: ResMut<PendingChunks>,
+    mut spawned_tiles: ResMut<SpawnedTiles>,
 ) {
-    let now = time.elapsed();
+    // This is the first system of the frame to spawn tiles.
+    spawned_tiles.0 = 0;
+    if !painter.kare.is_valid() {
+        return;
+    }
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    while spawned_tiles.0 < TILE_SPAWN_BUDGET {
+        let Some((coord, chunk)) = pending.generated.pop_front() else {
+            break;
+        };
+        let distance = chunk_distance(coord, player.translation, &painter.kare);
+        let lod = tile_lod.lod(None, distance, painter.kare.view_radius());
+        let entity = spawn_chunk(&mut commands, coord, chunk, lod, &mut painter);
+        loaded.0.insert(coord, entity);
+        spawned_tiles.0 += CHUNK_SIZE * CHUNK_SIZE;
+    }
+}
 
+/// Redraws the chunks whose distance to the player moved them to another [`Lod`],
+/// with what is left of the [`TILE_SPAWN_BUDGET`] after the newly spawned chunks.
+fn update_chunk_lod(
+    mut commands: Commands,
+    tile_lod: Res<TileLod>,
+    player: Query<&Transform, With<Player>>,
+    mut painter: ChunkPainter,
+    mut chunks: Query<(Entity, &mut Chunk, &mut ChunkTiles, &ChunkGround)>,
+    mut spawned_tiles: ResMut<SpawnedTiles>,
+) {
+    if !painter.kare.is_valid() {
+        return;
+    }
     let Ok(player) = player.get_single() else {
         return;
     };
-    if *last_sfx + TIME < now {
-        *last_sfx = now;
-        for (entity, location) in block.iter() {
-            if player.translation.distance(location.translation)
-                > (kare.bir_siradaki_kare_sayisi as f32 * kare.kare_kenar_uzunlugu as f32) / 1.5
-            {
-                commands.entity(entity).despawn();
-            }
+    for (entity, mut chunk, mut tiles, ground) in &mut chunks {
+        if spawned_tiles.0 >= TILE_SPAWN_BUDGET {
+            break;
+        }
+        let distance = chunk_distance(chunk.coord, player.translation, &painter.kare);
+        let lod = tile_lod.lod(Some(chunk.lod), distance, painter.kare.view_radius());
+        if lod == chunk.lod {
+            continue;
         }
+        commands.entity(tiles.visuals).despawn_recursive();
+        tiles.visuals = painter.spawn_visuals(&mut commands, &tiles.grid, ground, chunk.coord, lod);
+        commands.entity(entity).add_child(tiles.visuals);
+        chunk.lod = lod;
+        spawned_tiles.0 += CHUNK_SIZE * CHUNK_SIZE;
     }
 }
 
-fn run_if_empty_map(query: Query<(), With<Block>>) -> bool {
-    query.is_empty()
+/// Spawns the chunk at `coord` with the colliders of its tiles, drawn with `lod`.
+fn spawn_chunk(
+    commands: &mut Commands,
+    coord: IVec2,
+    chunk: GeneratedChunk,
+    lod: Lod,
+    painter: &mut ChunkPainter,
+) -> Entity {
+    let kare_kenar_uzunlugu = painter.kare.kare_kenar_uzunlugu as f32;
+    let origin = coord * CHUNK_SIZE as i32;
+    let ground = ChunkGround::new(&painter.rules, *painter.seed, coord, kare_kenar_uzunlugu);
+    let visuals = painter.spawn_visuals(commands, &chunk.grid, &ground, coord, lod);
+    let (ground_collider, ground_position) = ground.collider();
+
+    commands
+        .spawn((
+            Name::new("Chunk"),
+            Chunk {
+                coord,
+                fit: chunk.fit,
+                lod,
+            },
+            SpatialBundle::from_transform(Transform::from_translation(cell_to_world(
+                origin,
+                kare_kenar_uzunlugu,
+            ))),
+            StateScoped(Screen::Playing),
+        ))
+        .add_child(visuals)
+        .with_children(|children| {
+            children.spawn((
+                Name::new("Ground Collider"),
+                ground_collider,
+                TransformBundle::from_transform(Transform::from_translation(ground_position)),
+            ));
+            for (x, z, tile) in chunk.grid.iter() {
+                let model = &painter.asset_data.models[tile];
+                // Colliders are spawned next to the tile rather than on it,
+                // so they are not stretched by the scale of the model.
+                let Some(tile_collider) = &model.collider else {
+                    continue;
+                };
+                let scale = model.scale * Vec3::new(kare_kenar_uzunlugu, 1.0, kare_kenar_uzunlugu);
+                let Some((collider, position)) = tileset::build_collider(tile_collider, scale)
+                else {
+                    continue;
+                };
+                let mut center = cell_to_world(IVec2::new(x as i32, z as i32), kare_kenar_uzunlugu);
+                center.y = ground.height_at(center.xz());
+                let mut entity = children.spawn((
+                    Name::new("Tile Collider"),
+                    collider,
+                    TransformBundle::from_transform(
+                        Transform::from_translation(center + model.rotation * position)
+                            .with_rotation(model.rotation),
+                    ),
+                ));
+                if tile_collider.is_obstacle() {
+                    entity.insert(Obstacle);
+                }
+            }
+        })
+        .insert((
+            ChunkTiles {
+                grid: chunk.grid,
+                visuals,
+            },
+            ground,
+        ))
+        .id()
 }
 
-fn grid_to_world(x: f32, z: f32, kare_kenar_uzunlugu: usize) -> (f32, f32) {
-    (
-        x * kare_kenar_uzunlugu as f32,
-        z * kare_kenar_uzunlugu as f32,
-    )
+/// Everything needed to draw the tiles of a chunk.
+#[derive(SystemParam)]
+struct ChunkPainter<'w> {
+    kare: Res<'w, Kare>,
+    rules: Res<'w, WFCRules>,
+    seed: Res<'w, MapSeed>,
+    asset_data: Res<'w, AssetData>,
+    rendering: Res<'w, TileRendering>,
+    meshes: ResMut<'w, Assets<Mesh>>,
 }
 
-fn wfc_select_tile(rules: &WFCRules, neighbors: &[Option<TileType>]) -> TileType {
-    let mut possible_tiles: Vec<TileType> = vec![
-        TileType::Ground,
-        TileType::Wall,
-        TileType::Tree,
-        TileType::Column,
-    ];
+impl ChunkPainter<'_> {
+    /// Spawns the meshes of the chunk at `coord`, drawn with `lod`, relative to the chunk.
+    fn spawn_visuals(
+        &mut self,
+        commands: &mut Commands,
+        grid: &wfc::Grid,
+        ground: &ChunkGround,
+        coord: IVec2,
+        lod: Lod,
+    ) -> Entity {
+        let kare_kenar_uzunlugu = self.kare.kare_kenar_uzunlugu as f32;
+        let origin = coord * CHUNK_SIZE as i32;
+        let asset_data = &*self.asset_data;
+        let rendering = *self.rendering;
+        let meshes = &mut *self.meshes;
+
+        let mut visuals = commands.spawn((Name::new("Chunk Visuals"), SpatialBundle::default()));
+        visuals.with_children(|children| {
+            if lod > Lod::Near {
+                children.spawn((
+                    Name::new("Ground"),
+                    PbrBundle {
+                        mesh: meshes.add(ground.mesh()),
+                        material: asset_data.ground_material.clone(),
+                        transform: Transform::from_xyz(0.0, GROUND_OFFSET, 0.0),
+                        ..default()
+                    },
+                ));
+            }
+            if lod == Lod::Far {
+                return;
+            }
+
+            let mut floors = MeshMerger::default();
+            for (x, z, tile) in grid.iter() {
+                let offset = IVec2::new(x as i32, z as i32);
+                let model = &asset_data.models[tile];
+                let center = cell_to_world(offset, kare_kenar_uzunlugu);
+                let scale = model.scale * Vec3::new(kare_kenar_uzunlugu, 1.0, kare_kenar_uzunlugu);
+                let flat = Transform::from_translation(center)
+                    .with_rotation(model.rotation)
+                    .with_scale(scale);
+                let transform = flat.with_translation(center.with_y(ground.height_at(center.xz())));
+                let block = Block {
+                    id: tile as i32,
+                    cell: origin + offset,
+                };
+
+                // Tiles on slopes are bent to follow the ground, once their meshes are known.
+                if let Some(parts) = model.parts.as_ref().filter(|_| ground.is_slope(x, z)) {
+                    for part in parts {
+                        if let Some(mesh) = meshes.get(&part.mesh) {
+                            let mesh =
+                                ground.bend(mesh.clone().transformed_by(flat * part.transform));
+                            floors.add(&mesh, &part.material, Transform::IDENTITY);
+                        }
+                    }
+                    continue;
+                }
+
+                if lod == Lod::Mid && !model.floor {
+                    if let Some(proxy) = &model.proxy {
+                        children.spawn((
+                            Name::new("Tile Proxy"),
+                            PbrBundle {
+                                mesh: proxy.clone(),
+                                material: asset_data.proxy_material.clone(),
+                                transform,
+                                ..default()
+                            },
+                            block,
+                        ));
+                    }
+                    continue;
+                }
+
+                match model
+                    .parts
+                    .as_ref()
+                    .filter(|_| rendering == TileRendering::Merged)
+                {
+                    Some(parts) if model.floor => {
+                        for part in parts {
+                            if let Some(mesh) = meshes.get(&part.mesh) {
+                                floors.add(mesh, &part.material, transform * part.transform);
+                            }
+                        }
+                    }
+                    Some(parts) => {
+                        children
+                            .spawn((
+                                Name::new("Tile"),
+                                SpatialBundle::from_transform(transform),
+                                block,
+                            ))
+                            .with_children(|tile| {
+                                for part in parts {
+                                    tile.spawn(PbrBundle {
+                                        mesh: part.mesh.clone(),
+                                        material: part.material.clone(),
+                                        transform: part.transform,
+                                        ..default()
+                                    });
+                                }
+                            });
+                    }
+                    None => {
+                        children.spawn((
+                            Name::new("Tile"),
+                            SceneBundle {
+                                scene: model.scene.clone(),
+                                transform,
+                                ..default()
+                            },
+                            block,
+                        ));
+                    }
+                }
+            }
 
-    for neighbor in neighbors.iter().flatten() {
-        let allowed = rules.allowed_neighbors.get(neighbor).unwrap();
-        possible_tiles.retain(|t| allowed.contains(t));
+            for (mesh, material) in floors.finish() {
+                children.spawn((
+                    Name::new("Merged Tiles"),
+                    PbrBundle {
+                        mesh: meshes.add(mesh),
+                        material,
+                        ..default()
+                    },
+                ));
+            }
+        });
+        visuals.id()
     }
+}
 
-    *possible_tiles
-        .choose(&mut rand::thread_rng())
-        .unwrap_or(&TileType::Ground)
+fn clear_loaded_chunks(mut loaded: ResMut<LoadedChunks>, mut pending: ResMut<PendingChunks>) {
+    loaded.0.clear();
+    pending.clear();
 }
 
-fn calculate_tile_position(
-    player: &Transform,
-    world_x: f32,
-    world_z: f32,
-    kare: &Kare,
-) -> (f32, f32, f32) {
-    (
-        world_x - (kare.kare_kenar_uzunlugu as f32 * kare.bir_siradaki_kare_sayisi as f32 / 2.0)
-            + (((player.translation.x.round()) / kare.kare_kenar_uzunlugu as f32).round()
-                * kare.kare_kenar_uzunlugu as f32),
-        0.0,
-        world_z - (kare.kare_kenar_uzunlugu as f32 * kare.bir_siradaki_kare_sayisi as f32 / 2.0)
-            + (((player.translation.z.round()) / kare.kare_kenar_uzunlugu as f32).round()
-                * kare.kare_kenar_uzunlugu as f32),
-    )
+/// Every chunk with at least one tile within [`Kare::view_radius`] of `position`.
+fn chunks_in_view(position: Vec3, kare: &Kare) -> HashSet<IVec2> {
+    let kare_kenar_uzunlugu = kare.kare_kenar_uzunlugu as f32;
+    let chunk_length = CHUNK_SIZE as f32 * kare_kenar_uzunlugu;
+    let radius = kare.view_radius();
+    let center = position.xz();
+
+    let min = ((center - radius) / chunk_length).floor().as_ivec2();
+    let max = ((center + radius) / chunk_length).floor().as_ivec2();
+
+    let mut visible = HashSet::new();
+    for x in min.x..=max.x {
+        for z in min.y..=max.y {
+            let coord = IVec2::new(x, z);
+            if chunk_distance(coord, position, kare) <= radius {
+                visible.insert(coord);
+            }
+        }
+    }
+    visible
+}
+
+/// Distance from `position` to the closest tile center of the chunk at `coord`,
+/// ignoring height.
+fn chunk_distance(coord: IVec2, position: Vec3, kare: &Kare) -> f32 {
+    let kare_kenar_uzunlugu = kare.kare_kenar_uzunlugu as f32;
+    let first = (coord * CHUNK_SIZE as i32).as_vec2() * kare_kenar_uzunlugu;
+    let last = first + (CHUNK_SIZE - 1) as f32 * kare_kenar_uzunlugu;
+    let center = position.xz();
+    center.clamp(first, last).distance(center)
 }
 
-fn is_tile_in_range(player: &Transform, location_x: f32, location_z: f32, kare: &Kare) -> bool {
-    player
-        .translation
-        .distance(Vec3::new(location_x, 0.0, location_z))
-        <= (kare.bir_siradaki_kare_sayisi as f32 / 4.5) * kare.kare_kenar_uzunlugu as f32
+/// World position of the center of a grid cell, at the lowest level of the ground.
+fn cell_to_world(cell: IVec2, kare_kenar_uzunlugu: f32) -> Vec3 {
+    let position = cell.as_vec2() * kare_kenar_uzunlugu;
+    Vec3::new(position.x, 0.0, position.y)
 }
@@ -3,31 +3,60 @@
 use bevy::prelude::*;
 
 pub mod audio;
+pub mod bike_visuals;
+mod biome;
+pub mod crash;
+pub mod input;
+pub mod interpolation;
+pub mod lod;
 pub mod map_change;
-mod movement;
+pub mod movement;
 pub mod spawn;
+pub mod terrain;
+pub mod tile_mesh;
+pub mod tileset;
 
 pub(super) fn plugin(app: &mut App) {
     // Order new `GameSystem` variants by adding them here:
+    app.configure_sets(
+        FixedUpdate,
+        (GameSystem::Movement, GameSystem::Ground).chain(),
+    );
     app.configure_sets(
         Update,
-        (GameSystem::Movement, GameSystem::MapChange).chain(),
+        (GameSystem::Interpolation, GameSystem::MapChange).chain(),
     );
     app.add_plugins((
         audio::plugin,
+        bike_visuals::plugin,
+        biome::plugin,
+        crash::plugin,
+        input::plugin,
+        interpolation::plugin,
+        lod::plugin,
         movement::plugin,
         spawn::plugin,
+        terrain::plugin,
+        tileset::plugin,
         map_change::plugin,
     ));
 }
 
+/// How many times per second the simulation steps in `FixedUpdate`.
+/// The physics steps by the same amount of time.
+pub const SIMULATION_HZ: f64 = 64.0;
+
 /// High-level groupings of systems for your game.
 /// When adding a new variant, make sure to order it in the `configure_sets`
 /// call above.
 #[derive(SystemSet, Debug, Clone, Copy, Eq, PartialEq, Hash)]
 enum GameSystem {
-    /// Handles player movement.
+    /// Handles player movement, in `FixedUpdate`.
     Movement,
-    /// Handles map changes.
+    /// Keeps the player on the ground after it moves, in `FixedUpdate`.
+    Ground,
+    /// Handles map changes, in `Update`.
     MapChange,
+    /// Places what is drawn of the player between simulation steps.
+    Interpolation,
 }
@@ -1,114 +1,250 @@
-//! Handle player input and translate it into velocity.
-//! Note that the approach used here is simple for demonstration purposes.
-//! If you want to move the player in a smoother way,
-//! consider using a [fixed timestep](https://github.com/bevyengine/bevy/pull/14223).
+//! Handle player input and drive the motorbike with it.
+//!
+//! The bike keeps its speed and heading from one frame to the next. The throttle
+//! and brake change its speed, steering turns its heading, and the
+//! [`KinematicCharacterController`] moves it, so that it stops at obstacles and
+//! follows the ground.
+//!
+//! The bike is driven in `FixedUpdate`, so it handles the same at any frame rate.
+//! The camera follows its [`RenderPose`] to stay smooth between steps.
 
 use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
 use std::time::Duration;
 
-use super::{audio::sfx::Sfx, spawn::player::Player, GameSystem};
+use super::{
+    audio::sfx::Sfx,
+    input::{Action, ActionState},
+    interpolation::RenderPose,
+    spawn::player::Player,
+    GameSystem,
+};
 
-pub(super) fn plugin(app: &mut App) {
+pub fn plugin(app: &mut App) {
+    app.register_type::<(Bike, BikeInput, BikeTuning, VerticalVelocity)>();
+    app.init_resource::<BikeTuning>();
     app.add_systems(
-        Update,
-        (
-            handle_player_movement_input.in_set(GameSystem::Movement),
-            update_camera,
-        )
-            .chain(),
+        FixedUpdate,
+        (record_bike_input, drive_bike)
+            .chain()
+            .in_set(GameSystem::Movement)
+            .before(PhysicsSet::SyncBackend),
     );
+    app.add_systems(Update, update_camera.after(GameSystem::Interpolation));
 }
 
-/// Since Bevy's default 2D camera setup is scaled such that
-/// one unit is one pixel, you can think of this as
-/// "How many pixels per second should the player move?"
-/// Note that physics engines may use different unit/pixel ratios.
-const MOVEMENT_SPEED: f32 = 50.0;
+/// How fast the player falls, in units per second squared.
+const GRAVITY: f32 = 60.0;
 
-/// Time between walk sound effects.
-const STEP_SFX_INTERVAL: Duration = Duration::from_millis(250);
+/// How fast the player moves up or down. It is kept while the player is in the
+/// air, so riding up a slope that ends launches the bike.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default, Reflect)]
+#[reflect(Component)]
+pub struct VerticalVelocity(pub f32);
 
-/// Handle keyboard input to move the player.
-fn handle_player_movement_input(
-    time: Res<Time>,
-    input: Res<ButtonInput<KeyCode>>,
-    mut player_query: Query<&mut Transform, With<Player>>,
-    mut camera: Query<&mut Transform, (With<IsDefaultUiCamera>, Without<Player>)>,
-    mut last_sfx: Local<Duration>,
-    mut commands: Commands,
-) {
-    let mut intent = Vec3::ZERO;
-    if input.pressed(KeyCode::KeyW) || input.pressed(KeyCode::ArrowUp) {
-        intent.z -= 1.0;
-    }
-    if input.pressed(KeyCode::KeyS) || input.pressed(KeyCode::ArrowDown) {
-        intent.z += 0.5;
+/// How the motorbike handles.
+#[derive(Reflect, Resource, Clone, Copy, PartialEq, Debug)]
+#[reflect(Resource)]
+pub struct BikeTuning {
+    /// Highest forward speed, in units per second.
+    pub top_speed: f32,
+    /// Highest speed when riding backwards.
+    pub reverse_speed: f32,
+    /// How fast full throttle speeds up the bike from a standstill, in units per
+    /// second squared. It fades out as the bike nears its top speed.
+    pub acceleration: f32,
+    /// How fast the full brake slows the bike down.
+    pub braking: f32,
+    /// How fast the bike slows down on its own when neither is held.
+    pub coasting: f32,
+    /// How much boosting raises the top speed and acceleration, as a factor.
+    pub boost: f32,
+    /// How fast full steering turns the bike, in radians per second.
+    pub turn_rate: f32,
+    /// Below this speed the bike turns slower, and it cannot turn at all when stopped.
+    pub full_turn_speed: f32,
+}
+
+impl Default for BikeTuning {
+    fn default() -> Self {
+        Self {
+            top_speed: 50.0,
+            reverse_speed: 10.0,
+            acceleration: 25.0,
+            braking: 60.0,
+            coasting: 8.0,
+            boost: 1.4,
+            turn_rate: 2.0,
+            full_turn_speed: 8.0,
+        }
     }
-    if input.pressed(KeyCode::KeyA) || input.pressed(KeyCode::ArrowLeft) {
-        intent.x -= 0.8;
+}
+
+/// What the rider asks of the motorbike this frame.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default, Reflect)]
+#[reflect(Component)]
+pub struct BikeInput {
+    /// From 0 to 1.
+    pub throttle: f32,
+    /// From 0 to 1. Holding it while stopped rides backwards.
+    pub brake: f32,
+    /// From -1 for fully left to 1 for fully right.
+    pub steer: f32,
+    /// From 0 to 1.
+    pub boost: f32,
+}
+
+/// The motion of a motorbike.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default, Reflect)]
+#[reflect(Component)]
+pub struct Bike {
+    /// Speed along the heading, in units per second. Negative when riding backwards.
+    pub speed: f32,
+    /// Rotation around the vertical axis, in radians. The bike faces north (-z)
+    /// at zero, and turning right lowers it.
+    pub heading: f32,
+}
+
+impl Bike {
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_y(self.heading)
     }
-    if input.pressed(KeyCode::KeyD) || input.pressed(KeyCode::ArrowRight) {
-        intent.x += 0.8;
+
+    /// The horizontal direction the bike faces.
+    pub fn forward(&self) -> Vec3 {
+        self.rotation() * Vec3::NEG_Z
     }
 
-    // Rotation of the object
-    if input.just_pressed(KeyCode::KeyA) {
-        for mut transform in &mut player_query {
-            transform.rotate_z(0.15);
-        }
+    /// Advances the speed and heading of the bike by `delta` seconds of `input`.
+    pub fn step(&mut self, tuning: &BikeTuning, input: BikeInput, delta: f32) {
+        let throttle = input.throttle.clamp(0.0, 1.0);
+        let brake = input.brake.clamp(0.0, 1.0);
+        let boost = 1.0 + (tuning.boost - 1.0) * input.boost.clamp(0.0, 1.0);
+        let top_speed = tuning.top_speed * boost;
+        // Acceleration fades out as the bike nears its highest speed.
+        let taper = |fraction: f32| (1.0 - fraction * fraction).max(0.0);
 
-        for mut transform in &mut camera {
-            transform.rotate_z(0.05);
-        }
+        let (change, stopping) = if self.speed > 0.0 && brake > 0.0 {
+            (-tuning.braking * brake, true)
+        } else if self.speed < 0.0 && throttle > 0.0 {
+            (tuning.braking * throttle, true)
+        } else if throttle > brake && self.speed > top_speed {
+            // Going faster than the top speed after a boost ends.
+            (-tuning.coasting, false)
+        } else if throttle > brake {
+            let speed = self.speed / top_speed;
+            (
+                tuning.acceleration * boost * (throttle - brake) * taper(speed),
+                false,
+            )
+        } else if brake > throttle {
+            let speed = -self.speed / tuning.reverse_speed;
+            (
+                -tuning.acceleration * (brake - throttle) * taper(speed),
+                false,
+            )
+        } else {
+            (-self.speed.signum() * tuning.coasting, true)
+        };
+        let speed = self.speed + change * delta;
+        // Braking and coasting stop the bike rather than turn it around.
+        self.speed = if stopping && speed * self.speed <= 0.0 {
+            0.0
+        } else {
+            speed.clamp(
+                -tuning.reverse_speed,
+                tuning.top_speed * tuning.boost.max(1.0),
+            )
+        };
+
+        self.heading += self.turn_rate(tuning, input) * delta;
     }
-    if input.just_released(KeyCode::KeyA) {
-        for mut transform in &mut player_query {
-            transform.rotate_z(-0.15);
-        }
 
-        for mut transform in &mut camera {
-            transform.rotate_z(-0.05);
-        }
+    /// How fast `input` turns the bike at its current speed, in radians per
+    /// second. It is positive when turning left.
+    pub fn turn_rate(&self, tuning: &BikeTuning, input: BikeInput) -> f32 {
+        let grip = (self.speed.abs() / tuning.full_turn_speed).min(1.0);
+        let steer = input.steer.clamp(-1.0, 1.0);
+        -steer * tuning.turn_rate * grip * self.speed.signum()
     }
+}
 
-    if input.just_pressed(KeyCode::KeyD) {
-        for mut transform in &mut player_query {
-            transform.rotate_z(-0.15);
-        }
+/// Time between walk sound effects.
+const STEP_SFX_INTERVAL: Duration = Duration::from_millis(250);
 
-        for mut transform in &mut camera {
-            transform.rotate_z(-0.05);
-        }
+/// Reads the actions of the player into its [`BikeInput`].
+fn record_bike_input(actions: Res<ActionState>, mut player: Query<&mut BikeInput, With<Player>>) {
+    for mut bike_input in &mut player {
+        *bike_input = BikeInput {
+            throttle: actions.value(Action::Throttle),
+            brake: actions.value(Action::Brake),
+            steer: actions.value(Action::SteerRight) - actions.value(Action::SteerLeft),
+            boost: actions.value(Action::Boost),
+        };
     }
-    if input.just_released(KeyCode::KeyD) {
-        for mut transform in &mut player_query {
-            transform.rotate_z(0.15);
-        }
+}
 
-        for mut transform in &mut camera {
-            transform.rotate_z(0.05);
+/// Moves the player's bike with the character controller.
+fn drive_bike(
+    time: Res<Time>,
+    tuning: Res<BikeTuning>,
+    mut player: Query<
+        (
+            &mut Bike,
+            &BikeInput,
+            &mut Transform,
+            &mut KinematicCharacterController,
+            &mut VerticalVelocity,
+            Option<&KinematicCharacterControllerOutput>,
+        ),
+        With<Player>,
+    >,
+    mut last_sfx: Local<Duration>,
+    mut commands: Commands,
+) {
+    let delta = time.delta_seconds();
+    for (mut bike, &input, mut transform, mut controller, mut vertical, output) in &mut player {
+        if let Some(output) = output {
+            // On the ground, the player keeps the vertical speed the ground gave it.
+            if output.grounded {
+                vertical.0 = if delta > 0.0 {
+                    output.effective_translation.y / delta
+                } else {
+                    0.0
+                };
+            }
+            // Obstacles take away the speed they stopped, so the bike does not keep
+            // pushing against them.
+            let desired = output.desired_translation.xz().length();
+            if desired > f32::EPSILON {
+                let moved = output.effective_translation.xz().length();
+                bike.speed *= (moved / desired).min(1.0);
+            }
         }
-    }
-    // Need to normalize and scale because otherwise
-    // diagonal movement would be faster than horizontal or vertical movement.
-    //let intent = intent.normalize_or_zero();
-    let target_velocity = intent * MOVEMENT_SPEED;
 
-    for mut transform in &mut player_query {
-        transform.translation += target_velocity * time.delta_seconds();
-    }
+        bike.step(&tuning, input, delta);
+        transform.rotation = bike.rotation();
+
+        vertical.0 -= GRAVITY * delta;
+        let mut translation = bike.forward() * bike.speed * delta;
+        translation.y = vertical.0 * delta;
+        controller.translation = Some(translation);
+        // Snapping would pull the bike back down when it leaves the top of a slope.
+        controller.snap_to_ground = (vertical.0 <= 0.0).then_some(CharacterLength::Relative(0.5));
 
-    // If the player is moving, play a step sound effect.
-    let now = time.elapsed();
-    if intent != Vec3::ZERO && *last_sfx + STEP_SFX_INTERVAL < now {
-        *last_sfx = now;
-        commands.trigger(Sfx::Step);
+        // If the player is moving, play a step sound effect.
+        let now = time.elapsed();
+        if bike.speed.abs() > 1.0 && *last_sfx + STEP_SFX_INTERVAL < now {
+            *last_sfx = now;
+            commands.trigger(Sfx::Step);
+        }
     }
 }
 
+/// Keeps the camera behind the player, facing the way it rides.
 fn update_camera(
     mut camera: Query<&mut Transform, (With<IsDefaultUiCamera>, Without<Player>)>,
-    player: Query<&Transform, (With<Player>, Without<Camera3d>)>,
+    player: Query<&RenderPose, With<Player>>,
 ) {
     let Ok(mut camera) = camera.get_single_mut() else {
         return;
@@ -118,9 +254,7 @@ fn update_camera(
         return;
     };
 
-    //    println!("{:?}", player.translation);
-
-    let Vec3 { x, y, z } = player.translation;
-    let direction = Vec3::new(x, y + 2.3, z + 1.1);
-    camera.translation = direction;
+    let player = player.0;
+    camera.translation = player.translation + player.rotation * Vec3::new(0.0, 2.3, 1.1);
+    camera.rotation = player.rotation;
 }
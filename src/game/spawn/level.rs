@@ -3,15 +3,29 @@
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 
+use crate::game::SIMULATION_HZ;
+
 use super::map::SpawnMap;
 use super::player::SpawnPlayer;
 
 pub(super) fn plugin(app: &mut App) {
     app.observe(spawn_level)
-        .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+        .add_plugins(physics_plugin)
         .add_plugins(RapierDebugRenderPlugin::default());
 }
 
+/// Steps the physics in `FixedUpdate` along with the rest of the simulation,
+/// [`SIMULATION_HZ`] times per second.
+pub fn physics_plugin(app: &mut App) {
+    let dt = (1.0 / SIMULATION_HZ) as f32;
+    app.insert_resource(Time::<Fixed>::from_hz(SIMULATION_HZ))
+        .insert_resource(RapierConfiguration {
+            timestep_mode: TimestepMode::Fixed { dt, substeps: 1 },
+            ..RapierConfiguration::new(1.0)
+        })
+        .add_plugins(RapierPhysicsPlugin::<NoUserData>::default().in_fixed_schedule());
+}
+
 #[derive(Event, Debug)]
 pub struct SpawnLevel;
 
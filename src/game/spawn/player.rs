@@ -2,7 +2,14 @@
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 
-use crate::screen::Screen;
+use crate::{
+    game::{
+        bike_visuals::{BikeBody, BikeVisuals},
+        interpolation::{PreviousPose, RenderPose, SmoothedModel},
+        movement::{Bike, BikeInput, VerticalVelocity},
+    },
+    screen::Screen,
+};
 
 pub(super) fn plugin(app: &mut App) {
     app.observe(spawn_player);
@@ -12,6 +19,9 @@ pub(super) fn plugin(app: &mut App) {
 #[derive(Event, Debug)]
 pub struct SpawnPlayer;
 
+/// Half the height of the player's collider.
+const PLAYER_HALF_HEIGHT: f32 = 1.85;
+
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
 #[reflect(Component)]
 pub struct Player;
@@ -30,22 +40,45 @@ fn spawn_player(
         ..default()
     });
 
-    commands.spawn((
-        Name::new("Player"),
-        Player,
-        SceneBundle {
-            scene: asset_server.load("models/chopper_motorbike.glb#Scene0"),
-            transform: Transform::from_xyz(0.0, 0.0, 0.0),
-            ..default()
-        },
-        StateScoped(Screen::Playing),
-        RigidBody::KinematicVelocityBased,
-        Collider::cuboid(1.2, 1.85, 3.5),
-        LockedAxes::ROTATION_LOCKED,
-        KinematicCharacterController {
-            snap_to_ground: Some(CharacterLength::Relative(0.5)),
-            apply_impulse_to_dynamic_bodies: true,
-            ..default()
-        },
-    ));
+    // Rest the collider on the ground.
+    let transform = Transform::from_xyz(0.0, PLAYER_HALF_HEIGHT, 0.0);
+    commands
+        .spawn((
+            Name::new("Player"),
+            Player,
+            SpatialBundle::from_transform(transform),
+            PreviousPose(transform),
+            RenderPose(transform),
+            StateScoped(Screen::Playing),
+            RigidBody::KinematicVelocityBased,
+            Collider::cuboid(1.2, PLAYER_HALF_HEIGHT, 3.5),
+            LockedAxes::ROTATION_LOCKED,
+            Bike::default(),
+            BikeInput::default(),
+            BikeVisuals::default(),
+            VerticalVelocity::default(),
+            KinematicCharacterController {
+                snap_to_ground: Some(CharacterLength::Relative(0.5)),
+                apply_impulse_to_dynamic_bodies: true,
+                ..default()
+            },
+        ))
+        .with_children(|player| {
+            player
+                .spawn((
+                    Name::new("Player Model"),
+                    SmoothedModel,
+                    SpatialBundle::default(),
+                ))
+                .with_children(|model| {
+                    model.spawn((
+                        Name::new("Bike Body"),
+                        BikeBody,
+                        SceneBundle {
+                            scene: asset_server.load("models/chopper_motorbike.glb#Scene0"),
+                            ..default()
+                        },
+                    ));
+                });
+        });
 }
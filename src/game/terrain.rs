@@ -0,0 +1,215 @@
+//! The ground of the map, which rises in terraces joined by slopes as described by
+//! [`elevation`](crate::mapgen::elevation).
+//!
+//! Every chunk has a heightfield collider for its ground. Tiles stand on it, and
+//! the meshes of the tiles on slopes are bent to follow it.
+
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+        render_asset::RenderAssetUsages,
+    },
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::mapgen::{elevation, MapSeed, WFCRules, CHUNK_SIZE};
+
+use super::{
+    map_change::{Chunk, Kare},
+    spawn::player::Player,
+    GameSystem,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        FixedUpdate,
+        keep_player_above_ground
+            .in_set(GameSystem::Ground)
+            .before(PhysicsSet::SyncBackend),
+    );
+}
+
+/// Height of a level, as a fraction of the tile length,
+/// so slopes climb one level over the length of a tile.
+pub const LEVEL_RISE: f32 = 0.5;
+
+/// Number of cell corners along each side of a chunk.
+const CORNERS: usize = CHUNK_SIZE + 1;
+
+/// The shape of the ground of a [`Chunk`], relative to the chunk.
+///
+/// Each cell is split into two triangles along the diagonal from its south-west
+/// to its north-east corner, the same way the heightfield collider is.
+#[derive(Component, Clone, Debug)]
+pub struct ChunkGround {
+    /// The height of every cell corner, in rows from north to south.
+    heights: Vec<f32>,
+    tile_length: f32,
+}
+
+impl ChunkGround {
+    pub fn new(rules: &WFCRules, seed: MapSeed, coord: IVec2, tile_length: f32) -> Self {
+        let level_height = LEVEL_RISE * tile_length;
+        Self {
+            heights: elevation::chunk_corners(rules, seed, coord)
+                .into_iter()
+                .map(|level| level as f32 * level_height)
+                .collect(),
+            tile_length,
+        }
+    }
+
+    /// The height of the north-west corner of cell `(x, z)`.
+    fn corner(&self, x: usize, z: usize) -> f32 {
+        self.heights[z * CORNERS + x]
+    }
+
+    /// The height of the ground at `position`, relative to the chunk.
+    /// Positions outside of the chunk get the height of its closest border.
+    pub fn height_at(&self, position: Vec2) -> f32 {
+        let (cell, t) = self.locate(position);
+        let corner = |x: usize, z: usize| self.corner(cell.x as usize + x, cell.y as usize + z);
+        if t.x + t.y <= 1.0 {
+            corner(0, 0) + t.x * (corner(1, 0) - corner(0, 0)) + t.y * (corner(0, 1) - corner(0, 0))
+        } else {
+            corner(1, 1)
+                + (1.0 - t.x) * (corner(0, 1) - corner(1, 1))
+                + (1.0 - t.y) * (corner(1, 0) - corner(1, 1))
+        }
+    }
+
+    /// The direction the ground faces at `position`, relative to the chunk.
+    pub fn normal_at(&self, position: Vec2) -> Vec3 {
+        let (cell, t) = self.locate(position);
+        let corner = |x: usize, z: usize| self.corner(cell.x as usize + x, cell.y as usize + z);
+        let (slope_x, slope_z) = if t.x + t.y <= 1.0 {
+            (corner(1, 0) - corner(0, 0), corner(0, 1) - corner(0, 0))
+        } else {
+            (corner(1, 1) - corner(0, 1), corner(1, 1) - corner(1, 0))
+        };
+        Vec3::new(-slope_x, self.tile_length, -slope_z).normalize()
+    }
+
+    /// The cell whose corners surround `position`, and how far into it the position is.
+    fn locate(&self, position: Vec2) -> (UVec2, Vec2) {
+        // Corners sit half a tile before the center of their cell.
+        let corners =
+            (position / self.tile_length + 0.5).clamp(Vec2::ZERO, Vec2::splat(CHUNK_SIZE as f32));
+        let cell = corners.floor().min(Vec2::splat(CHUNK_SIZE as f32 - 1.0));
+        (cell.as_uvec2(), corners - cell)
+    }
+
+    /// Returns `true` if cell `(x, z)` of the chunk is not level.
+    pub fn is_slope(&self, x: usize, z: usize) -> bool {
+        let height = self.corner(x, z);
+        [(1, 0), (0, 1), (1, 1)]
+            .into_iter()
+            .any(|(dx, dz)| self.corner(x + dx, z + dz) != height)
+    }
+
+    /// A heightfield collider covering the chunk, and its position relative to the chunk.
+    pub fn collider(&self) -> (Collider, Vec3) {
+        // The heightfield is stored in columns from west to east.
+        let heights = (0..CORNERS)
+            .flat_map(|x| (0..CORNERS).map(move |z| self.corner(x, z)))
+            .collect();
+        let length = CHUNK_SIZE as f32 * self.tile_length;
+        let center = (CHUNK_SIZE - 1) as f32 * self.tile_length / 2.0;
+        (
+            Collider::heightfield(heights, CORNERS, CORNERS, Vec3::new(length, 1.0, length)),
+            Vec3::new(center, 0.0, center),
+        )
+    }
+
+    /// A mesh of the whole ground of the chunk, relative to the chunk.
+    pub fn mesh(&self) -> Mesh {
+        let position = |x: usize, z: usize| {
+            let position = (Vec2::new(x as f32, z as f32) - 0.5) * self.tile_length;
+            Vec3::new(position.x, self.corner(x, z), position.y)
+        };
+        let positions: Vec<_> = (0..CORNERS)
+            .flat_map(|z| (0..CORNERS).map(move |x| (x, z)))
+            .map(|(x, z)| position(x, z).to_array())
+            .collect();
+        let normals: Vec<_> = positions
+            .iter()
+            .map(|&[x, _, z]| self.normal_at(Vec2::new(x, z)).to_array())
+            .collect();
+        let uvs: Vec<_> = positions.iter().map(|_| [0.0, 0.0]).collect();
+        let mut indices = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE * 6);
+        for z in 0..CHUNK_SIZE as u32 {
+            for x in 0..CHUNK_SIZE as u32 {
+                let corner = |dx: u32, dz: u32| (z + dz) * CORNERS as u32 + x + dx;
+                indices.extend([corner(0, 0), corner(0, 1), corner(1, 0)]);
+                indices.extend([corner(1, 0), corner(0, 1), corner(1, 1)]);
+            }
+        }
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+
+    /// Raises every vertex of `mesh`, given relative to the chunk, by the height of the
+    /// ground below it and tilts its normal along with the ground.
+    pub fn bend(&self, mut mesh: Mesh) -> Mesh {
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+        else {
+            return mesh;
+        };
+        let grounds: Vec<_> = positions
+            .iter_mut()
+            .map(|position| {
+                let ground = Vec2::new(position[0], position[2]);
+                position[1] += self.height_at(ground);
+                ground
+            })
+            .collect();
+        if let Some(VertexAttributeValues::Float32x3(normals)) =
+            mesh.attribute_mut(Mesh::ATTRIBUTE_NORMAL)
+        {
+            for (normal, ground) in normals.iter_mut().zip(grounds) {
+                let tilt = Quat::from_rotation_arc(Vec3::Y, self.normal_at(ground));
+                *normal = (tilt * Vec3::from_array(*normal)).to_array();
+            }
+        }
+        mesh
+    }
+}
+
+/// Lifts the player back onto the ground when it ends up below it, such as when
+/// it spawns or the map is rebuilt with another seed.
+fn keep_player_above_ground(
+    kare: Res<Kare>,
+    chunks: Query<(&Chunk, &ChunkGround, &GlobalTransform)>,
+    mut player: Query<(&mut Transform, &Collider), With<Player>>,
+) {
+    let Ok((mut transform, collider)) = player.get_single_mut() else {
+        return;
+    };
+    if !kare.is_valid() {
+        return;
+    }
+    let tile_length = kare.kare_kenar_uzunlugu as f32;
+    let cell = (transform.translation.xz() / tile_length)
+        .round()
+        .as_ivec2();
+    let coord = cell.div_euclid(IVec2::splat(CHUNK_SIZE as i32));
+    let Some((_, ground, chunk_transform)) = chunks.iter().find(|(chunk, ..)| chunk.coord == coord)
+    else {
+        return;
+    };
+
+    let position = transform.translation.xz() - chunk_transform.translation().xz();
+    let ground = chunk_transform.translation().y + ground.height_at(position);
+    let bottom = transform.translation.y + collider.raw.compute_local_aabb().mins.y;
+    if bottom < ground {
+        transform.translation.y += ground - bottom;
+    }
+}
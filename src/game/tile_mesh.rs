@@ -0,0 +1,100 @@
+//! Draws tiles straight from the meshes of their models instead of spawning a
+//! copy of their glTF scene per tile. See [`TileRendering`].
+
+use bevy::{
+    prelude::*,
+    render::mesh::{Indices, MeshVertexAttributeId, PrimitiveTopology},
+    utils::HashMap,
+};
+
+/// How the tiles of a chunk are drawn.
+#[derive(Reflect, Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[reflect(Resource)]
+pub enum TileRendering {
+    /// Every tile spawns its own copy of its glTF scene.
+    #[default]
+    Scenes,
+    /// The floor tiles of each chunk are merged into one mesh per material, and
+    /// every other tile is a single entity per mesh that shares the mesh and material
+    /// of its model, so the renderer can batch them. Tiles whose model has not
+    /// finished loading yet still spawn their scene.
+    Merged,
+}
+
+/// A mesh of a tile model, placed where the model's scene puts it.
+#[derive(Clone)]
+pub(super) struct TilePart {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+    pub transform: Transform,
+}
+
+/// Every mesh in the scene of a loaded model.
+pub(super) fn scene_parts(scene: &Scene) -> Vec<TilePart> {
+    let world = &scene.world;
+    world
+        .iter_entities()
+        .filter_map(|entity| {
+            let mesh = entity.get::<Handle<Mesh>>()?;
+            let material = entity.get::<Handle<StandardMaterial>>()?;
+            let mut transform = entity.get::<Transform>().copied().unwrap_or_default();
+            let mut parent = entity.get::<Parent>();
+            while let Some(entity) = parent.map(|parent| world.entity(parent.get())) {
+                transform = entity.get::<Transform>().copied().unwrap_or_default() * transform;
+                parent = entity.get::<Parent>();
+            }
+            Some(TilePart {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform,
+            })
+        })
+        .collect()
+}
+
+/// Only meshes with the same layout can be merged.
+#[derive(PartialEq, Eq, Hash)]
+struct MergeKey {
+    material: AssetId<StandardMaterial>,
+    topology: PrimitiveTopology,
+    attributes: Vec<MeshVertexAttributeId>,
+    indexed: bool,
+}
+
+/// Collects meshes into as few meshes as possible, one per material.
+#[derive(Default)]
+pub(super) struct MeshMerger {
+    meshes: HashMap<MergeKey, (Handle<StandardMaterial>, Mesh)>,
+}
+
+impl MeshMerger {
+    /// Adds `mesh`, moved by `transform`.
+    pub fn add(&mut self, mesh: &Mesh, material: &Handle<StandardMaterial>, transform: Transform) {
+        let key = MergeKey {
+            material: material.id(),
+            topology: mesh.primitive_topology(),
+            attributes: mesh.attributes().map(|(id, _)| id).collect(),
+            indexed: mesh.indices().is_some(),
+        };
+        let mesh = mesh.clone().transformed_by(transform);
+        match self.meshes.get_mut(&key) {
+            Some((_, merged)) => merged.merge(&mesh),
+            None => {
+                // Merged meshes quickly outgrow 16-bit indices.
+                let mut mesh = mesh;
+                if let Some(Indices::U16(indices)) = mesh.indices() {
+                    let indices = indices.iter().map(|&index| u32::from(index)).collect();
+                    mesh.insert_indices(Indices::U32(indices));
+                }
+                self.meshes.insert(key, (material.clone(), mesh));
+            }
+        }
+    }
+
+    /// The merged meshes and their materials.
+    pub fn finish(self) -> impl Iterator<Item = (Mesh, Handle<StandardMaterial>)> {
+        self.meshes
+            .into_values()
+            .map(|(material, mesh)| (mesh, material))
+    }
+}
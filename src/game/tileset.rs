@@ -0,0 +1,104 @@
+//! Loads the tileset the map is generated from as an asset.
+//! In native dev builds the tileset is hot-reloaded when its file changes.
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::mapgen::tileset::{TileCollider, Tileset, TilesetError};
+
+pub fn plugin(app: &mut App) {
+    app.init_asset::<TilesetAsset>()
+        .init_asset_loader::<TilesetLoader>()
+        .init_resource::<TilesetHandle>();
+}
+
+/// The tileset the map is generated from.
+const TILESET_PATH: &str = "tilesets/default.tileset.ron";
+
+/// A [`Tileset`] loaded by the asset server.
+#[derive(Asset, TypePath, Debug, Clone, Deref)]
+pub struct TilesetAsset(pub Tileset);
+
+/// Builds the collider of `shape` for a model with the given `scale`, and returns it
+/// with its position relative to the center of the unrotated tile. The ground has
+/// none, since every chunk has a single collider for its whole ground.
+pub fn build_collider(shape: &TileCollider, scale: Vec3) -> Option<(Collider, Vec3)> {
+    match *shape {
+        TileCollider::Ground => None,
+        TileCollider::Cuboid {
+            half_extents,
+            offset: [x, z],
+        } => {
+            let half_extents = Vec3::from_array(half_extents) * scale;
+            Some((
+                Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+                Vec3::new(x * scale.x, half_extents.y, z * scale.z),
+            ))
+        }
+        TileCollider::Capsule { radius, height } => {
+            let radius = radius * scale.x.max(scale.z);
+            let half_height = height * scale.y / 2.0;
+            Some((
+                Collider::capsule_y((half_height - radius).max(0.0), radius),
+                Vec3::new(0.0, half_height, 0.0),
+            ))
+        }
+    }
+}
+
+/// A simple mesh with the shape of `shape`, in the units of the model,
+/// used to draw obstacles far away. The ground has none.
+pub fn proxy_mesh(shape: &TileCollider) -> Option<Mesh> {
+    match *shape {
+        TileCollider::Ground => None,
+        TileCollider::Cuboid {
+            half_extents,
+            offset: [x, z],
+        } => {
+            let half_extents = Vec3::from_array(half_extents);
+            let mesh = Mesh::from(Cuboid::from_size(half_extents * 2.0));
+            Some(mesh.translated_by(Vec3::new(x, half_extents.y, z)))
+        }
+        TileCollider::Capsule { radius, height } => {
+            let mesh = Mesh::from(Capsule3d::new(radius, (height - 2.0 * radius).max(0.0)));
+            Some(mesh.translated_by(Vec3::new(0.0, height / 2.0, 0.0)))
+        }
+    }
+}
+
+#[derive(Default)]
+struct TilesetLoader;
+
+impl AssetLoader for TilesetLoader {
+    type Asset = TilesetAsset;
+    type Settings = ();
+    type Error = TilesetError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TilesetAsset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Tileset::from_ron(&bytes).map(TilesetAsset)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tileset.ron"]
+    }
+}
+
+/// Keeps the active tileset loaded.
+#[derive(Resource)]
+pub struct TilesetHandle(pub Handle<TilesetAsset>);
+
+impl FromWorld for TilesetHandle {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.resource::<AssetServer>().load(TILESET_PATH))
+    }
+}
@@ -1,9 +1,11 @@
 mod camera;
 #[cfg(feature = "dev")]
 mod dev_tools;
-mod game;
+pub mod game;
+pub mod mapgen;
 mod screen;
 mod ui_tools;
+pub mod wfc;
 
 /*use bevy::core::TaskPoolThreadAssignmentPolicy;
 use bevy::tasks::available_parallelism;*/
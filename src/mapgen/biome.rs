@@ -0,0 +1,102 @@
+//! Biomes split the world into regions that each use their own mix of tiles,
+//! such as ruins, forest and open plain.
+//!
+//! The biome of a cell is picked by low-frequency noise over the grid, so biomes
+//! form large patches. Near the border between two biomes their tile weights are
+//! blended, so one biome fades into the next instead of ending abruptly.
+
+use bevy::{color::Color, math::prelude::*};
+
+use super::{tileset::Tileset, MapSeed};
+
+/// Random stream of [`MapSeed`] used for the biome noise.
+const NOISE_STREAM: u64 = 7;
+
+/// Roughly how many cells wide a biome is.
+const BIOME_SIZE: f32 = 40.0;
+
+/// Fraction of each biome's share of the noise that blends into its neighbours.
+const TRANSITION: f32 = 0.5;
+
+/// A biome of the loaded [`Tileset`].
+#[derive(Debug, Clone)]
+pub struct Biome {
+    pub id: String,
+    /// The weight of every tile in this biome, indexed by [`TileType::id`](super::TileType::id).
+    /// Tiles the biome does not use have a weight of zero.
+    pub weights: Vec<f32>,
+    pub ambient: Color,
+}
+
+impl Biome {
+    pub fn from_tileset(tileset: &Tileset) -> Vec<Self> {
+        let variants = tileset.variants();
+        tileset
+            .biomes
+            .iter()
+            .map(|biome| Biome {
+                id: biome.id.clone(),
+                weights: variants
+                    .iter()
+                    .map(|variant| biome.weight(variant))
+                    .collect(),
+                ambient: Color::srgb_from_array(biome.ambient),
+            })
+            .collect()
+    }
+}
+
+/// How much each biome contributes to `cell`, out of `biome_count` biomes.
+/// Returns two biomes whose influences add up to one. Away from biome borders,
+/// the second one has no influence.
+pub fn biomes_at(seed: MapSeed, cell: IVec2, biome_count: usize) -> [(usize, f32); 2] {
+    let position = noise(seed, cell) * biome_count as f32;
+    let biome = (position as usize).min(biome_count - 1);
+    let within = position - biome as f32;
+
+    // Fade into the previous or next biome when close to either end of this one.
+    let (neighbour, distance) = if within < 0.5 {
+        (biome.checked_sub(1), within)
+    } else {
+        (
+            Some(biome + 1).filter(|&next| next < biome_count),
+            1.0 - within,
+        )
+    };
+    let influence = match neighbour {
+        Some(_) => 0.5 * (1.0 - smoothstep(distance / (TRANSITION / 2.0))),
+        None => 0.0,
+    };
+    [
+        (biome, 1.0 - influence),
+        (neighbour.unwrap_or(biome), influence),
+    ]
+}
+
+/// Smooth noise in `0.0..1.0` that changes over about [`BIOME_SIZE`] cells.
+fn noise(seed: MapSeed, cell: IVec2) -> f32 {
+    let position = cell.as_vec2() / BIOME_SIZE;
+    let value = 0.75 * value_noise(seed, position, 0) + 0.25 * value_noise(seed, position * 2.0, 1);
+    // Spread the values out, since summed noise clusters around the middle.
+    ((value - 0.5) * 1.8 + 0.5).clamp(0.0, 0.999)
+}
+
+/// Interpolates random values placed at the integer points of the plane.
+/// Each `octave` is an independent layer of noise.
+pub(super) fn value_noise(seed: MapSeed, position: Vec2, octave: u64) -> f32 {
+    let corner = position.floor();
+    let t = (position - corner).to_array().map(smoothstep);
+    let corner = corner.as_ivec2();
+    let value = |offset: IVec2| {
+        let hash = seed.hash_at(NOISE_STREAM ^ (octave << 32), corner + offset);
+        (hash >> 40) as f32 / (1u64 << 24) as f32
+    };
+    let north = value(IVec2::ZERO).lerp(value(IVec2::X), t[0]);
+    let south = value(IVec2::Y).lerp(value(IVec2::ONE), t[0]);
+    north.lerp(south, t[1])
+}
+
+fn smoothstep(x: f32) -> f32 {
+    let x = x.clamp(0.0, 1.0);
+    x * x * (3.0 - 2.0 * x)
+}
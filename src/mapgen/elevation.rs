@@ -0,0 +1,63 @@
+//! Elevation lifts the world into terraces joined by slopes.
+//!
+//! Every cell has a level, picked by low-frequency noise so that terraces form
+//! large patches. The corners of the cells take the highest level of the cells
+//! around them, so a cell next to a higher one slopes up towards it and the ground
+//! never has a step. Only tiles that may lie on a slope are placed on those cells.
+//!
+//! Tilesets without such tiles generate a flat world.
+
+use bevy::math::prelude::*;
+
+use super::{biome::value_noise, MapSeed, WFCRules, CHUNK_SIZE};
+
+/// Layer of the biome noise used for the levels.
+const ELEVATION_OCTAVE: u64 = 2;
+
+/// The highest level. The lowest one is zero.
+pub const MAX_LEVEL: i32 = 3;
+
+/// Roughly how many cells wide a terrace is. The noise changes slowly enough over
+/// this size that neighbouring cells are never more than one level apart.
+const TERRACE_SIZE: f32 = 24.0;
+
+/// The level of `cell`.
+pub fn level_at(rules: &WFCRules, seed: MapSeed, cell: IVec2) -> i32 {
+    if !rules.has_slopes() {
+        return 0;
+    }
+    let value = value_noise(seed, cell.as_vec2() / TERRACE_SIZE, ELEVATION_OCTAVE);
+    // Spread the values out, since the noise clusters around the middle.
+    let value = ((value - 0.5) * 1.6 + 0.5).clamp(0.0, 0.999);
+    (value * (MAX_LEVEL + 1) as f32) as i32
+}
+
+/// The level of the north-west corner of `cell`: the highest level of the four
+/// cells that share it.
+pub fn corner_level(rules: &WFCRules, seed: MapSeed, cell: IVec2) -> i32 {
+    [IVec2::ZERO, IVec2::X, IVec2::Y, IVec2::ONE]
+        .into_iter()
+        .map(|offset| level_at(rules, seed, cell - offset))
+        .max()
+        .unwrap_or_default()
+}
+
+/// Returns `true` if the ground of `cell` is not level, because a cell around it
+/// is higher.
+pub fn is_slope(rules: &WFCRules, seed: MapSeed, cell: IVec2) -> bool {
+    let level = level_at(rules, seed, cell);
+    (-1..=1)
+        .flat_map(|x| (-1..=1).map(move |z| IVec2::new(x, z)))
+        .any(|offset| level_at(rules, seed, cell + offset) > level)
+}
+
+/// The level of every cell corner of the chunk at `coord`, including the corners
+/// along its south and east borders. There are `CHUNK_SIZE + 1` rows from north to
+/// south of `CHUNK_SIZE + 1` corners from west to east.
+pub fn chunk_corners(rules: &WFCRules, seed: MapSeed, coord: IVec2) -> Vec<i32> {
+    let origin = coord * CHUNK_SIZE as i32;
+    (0..=CHUNK_SIZE as i32)
+        .flat_map(|z| (0..=CHUNK_SIZE as i32).map(move |x| IVec2::new(x, z)))
+        .map(|corner| corner_level(rules, seed, origin + corner))
+        .collect()
+}
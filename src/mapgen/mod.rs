@@ -0,0 +1,510 @@
+//! Generates the tiles of the world from a seed and a [`Tileset`].
+//! The world is split into chunks of [`CHUNK_SIZE`] by [`CHUNK_SIZE`] cells that
+//! can be generated in any order and still fit together.
+//!
+//! This module has no systems, components or assets and does not use the game
+//! module: the game runs it on a task pool and spawns the resulting grids, and
+//! the `mapgen` binary prints them. The only ECS items are the `Resource` and
+//! `Reflect` derives that let the game store [`MapSeed`] and [`WFCRules`] as is.
+
+pub mod biome;
+pub mod elevation;
+pub mod output;
+pub mod road;
+pub mod structure;
+pub mod tileset;
+
+use bevy::{
+    log::warn,
+    math::IVec2,
+    prelude::{ReflectResource, Resource},
+    reflect::Reflect,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::{HashMap, HashSet};
+
+use crate::wfc::{self, Contradiction, Direction, Ruleset};
+
+use biome::Biome;
+use structure::Structure;
+use tileset::Tileset;
+
+/// Number of tiles along each side of a chunk.
+pub const CHUNK_SIZE: usize = 8;
+
+/// Seed of the generated world.
+/// The same seed always generates the same tiles around the same coordinates.
+#[derive(Reflect, Resource, Clone, Copy, PartialEq, Eq, Debug)]
+#[reflect(Resource)]
+pub struct MapSeed(pub u32);
+
+impl Default for MapSeed {
+    fn default() -> Self {
+        Self(rand::random())
+    }
+}
+
+impl MapSeed {
+    /// A deterministic RNG for the map area whose grid origin is `cell`.
+    pub fn rng_at(self, cell: IVec2) -> StdRng {
+        self.rng(cell_stream(cell))
+    }
+
+    /// A deterministic RNG for an independent random stream, such as sound effects.
+    pub fn rng(self, stream: u64) -> StdRng {
+        StdRng::seed_from_u64(splitmix64(splitmix64(u64::from(self.0)) ^ stream))
+    }
+
+    /// A deterministic random number for `cell` in the random stream `stream`.
+    /// Cheaper than [`MapSeed::rng_at`] when a single number per cell is enough.
+    pub fn hash_at(self, stream: u64, cell: IVec2) -> u64 {
+        splitmix64(splitmix64(splitmix64(u64::from(self.0)) ^ stream) ^ cell_stream(cell))
+    }
+}
+
+/// Packs both coordinates of `cell` into a single random stream id.
+fn cell_stream(cell: IVec2) -> u64 {
+    u64::from(cell.x as u32) << 32 | u64::from(cell.y as u32)
+}
+
+/// Scrambles the bits of `x` so that nearby inputs give unrelated outputs.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// A tile of the loaded [`Tileset`] in one of its rotations,
+/// identified by its index in [`Tileset::variants`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TileType(usize);
+
+impl TileType {
+    /// The id of this tile in the solver's [`Ruleset`].
+    pub fn id(self) -> usize {
+        self.0
+    }
+
+    pub fn from_id(id: usize) -> Self {
+        Self(id)
+    }
+}
+
+/// Adjacency rules and weights of the loaded [`Tileset`].
+/// Empty until the tileset has finished loading.
+#[derive(Resource, Default, Clone)]
+pub struct WFCRules {
+    /// `allowed_neighbors[a][d]` lists every tile that may sit on side `d` of tile `a`.
+    allowed_neighbors: Vec<[Vec<TileType>; 4]>,
+    /// Relative probability of each tile, indexed by [`TileType::id`].
+    weights: Vec<f32>,
+    /// Whether each tile may be part of a road, indexed by [`TileType::id`].
+    drivable: Vec<bool>,
+    /// Whether each tile may lie on a slope, indexed by [`TileType::id`].
+    slopes: Vec<bool>,
+    biomes: Vec<Biome>,
+    structures: Vec<Structure>,
+    ruleset: Ruleset,
+}
+
+impl WFCRules {
+    /// Matches the sockets of every pair of tile variants.
+    pub fn from_tileset(tileset: &Tileset) -> Self {
+        let variants = tileset.variants();
+        let allowed_neighbors = variants
+            .iter()
+            .map(|a| {
+                Direction::ALL.map(|direction| {
+                    let socket = a.socket(direction);
+                    variants
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, b)| tileset.connects(socket, b.socket(direction.opposite())))
+                        .map(|(index, _)| TileType(index))
+                        .collect()
+                })
+            })
+            .collect();
+        let weights = variants.iter().map(|variant| variant.weight()).collect();
+        let drivable = variants
+            .iter()
+            .map(|variant| variant.definition.drivable)
+            .collect();
+        let slopes = variants
+            .iter()
+            .map(|variant| variant.definition.slope)
+            .collect();
+        let mut rules = Self {
+            allowed_neighbors,
+            weights,
+            drivable,
+            slopes,
+            biomes: Biome::from_tileset(tileset),
+            structures: Structure::from_tileset(tileset),
+            ruleset: Ruleset::default(),
+        };
+        rules.ruleset = rules.build_ruleset();
+        rules
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.allowed_neighbors.len()
+    }
+
+    /// How likely `tile` is relative to the other tiles.
+    pub fn weight(&self, tile: TileType) -> f32 {
+        self.weights[tile.0]
+    }
+
+    /// Returns `true` if `b` may sit on side `direction` of `a`.
+    pub fn allows(&self, a: TileType, direction: Direction, b: TileType) -> bool {
+        self.allowed_neighbors[a.0][direction as usize].contains(&b)
+    }
+
+    /// Returns `true` if the bike can ride over `tile`.
+    pub fn is_drivable(&self, tile: TileType) -> bool {
+        self.drivable[tile.0]
+    }
+
+    /// Every tile a road can be built from.
+    pub fn drivable_tiles(&self) -> impl Iterator<Item = TileType> + '_ {
+        (0..self.tile_count())
+            .map(TileType)
+            .filter(|&tile| self.is_drivable(tile))
+    }
+
+    /// Returns `true` if `tile` may lie on a slope.
+    pub fn fits_slope(&self, tile: TileType) -> bool {
+        self.slopes[tile.0]
+    }
+
+    /// Returns `true` if any tile may lie on a slope. Otherwise the world is flat.
+    pub fn has_slopes(&self) -> bool {
+        self.slopes.contains(&true)
+    }
+
+    pub fn biomes(&self) -> &[Biome] {
+        &self.biomes
+    }
+
+    pub fn structures(&self) -> &[Structure] {
+        &self.structures
+    }
+
+    /// The tile weights at `cell`, blended from the biomes around it,
+    /// or `None` if the tileset has no biomes.
+    pub fn cell_weights(&self, seed: MapSeed, cell: IVec2) -> Option<Vec<f32>> {
+        if self.biomes.is_empty() {
+            return None;
+        }
+        let [(a, a_influence), (b, b_influence)] = biome::biomes_at(seed, cell, self.biomes.len());
+        let weights = self.biomes[a]
+            .weights
+            .iter()
+            .zip(&self.biomes[b].weights)
+            .map(|(a, b)| a * a_influence + b * b_influence)
+            .collect();
+        Some(weights)
+    }
+
+    /// Every cell of `tiles` whose tile does not fit one of its neighbours,
+    /// such as cells next to a generation fallback or a cleared road.
+    pub fn contradictions(&self, tiles: &HashMap<IVec2, TileType>) -> HashSet<IVec2> {
+        let mut cells = HashSet::new();
+        for (&cell, &tile) in tiles {
+            for direction in [Direction::East, Direction::South] {
+                let (dx, dz) = direction.offset();
+                let neighbour = cell + IVec2::new(dx as i32, dz as i32);
+                let Some(&other) = tiles.get(&neighbour) else {
+                    continue;
+                };
+                if !self.allows(tile, direction, other) {
+                    cells.extend([cell, neighbour]);
+                }
+            }
+        }
+        cells
+    }
+
+    /// The rules in the form the solver uses.
+    pub fn ruleset(&self) -> &Ruleset {
+        &self.ruleset
+    }
+
+    fn build_ruleset(&self) -> Ruleset {
+        let tile_count = self.tile_count();
+        let mut ruleset = Ruleset::new(tile_count);
+        for (tile, &weight) in self.weights.iter().enumerate() {
+            ruleset.set_weight(tile, weight);
+        }
+        for (a, allowed) in self.allowed_neighbors.iter().enumerate() {
+            for direction in Direction::ALL {
+                for b in &allowed[direction as usize] {
+                    ruleset.allow(a, direction, b.id());
+                }
+            }
+        }
+        ruleset
+    }
+}
+
+/// How well the tiles of a generated chunk fit the edges it shares with its neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum ChunkFit {
+    /// The chunk was solved with its shared edges at the first attempt.
+    #[default]
+    Seamless,
+    /// The first attempt ran into a contradiction, but a retry fit the shared edges.
+    Retried,
+    /// The chunk could not be fit to its neighbours and was solved on its own,
+    /// so the rules may not hold along its borders.
+    Unconstrained,
+}
+
+/// The tiles of a chunk and how well they fit its neighbours.
+#[derive(Debug, Clone)]
+pub struct GeneratedChunk {
+    pub grid: wfc::Grid,
+    pub fit: ChunkFit,
+}
+
+/// Generates the tiles of the chunk at `coord`, with a drivable road running through it.
+///
+/// If the chunk cannot be made to fit its neighbours, it is generated on its own,
+/// and the returned [`ChunkFit`] records that its borders may break the rules.
+/// If its road still ends up blocked, the road is cleared by replacing its tiles.
+pub fn generate_chunk(
+    rules: &WFCRules,
+    seed: MapSeed,
+    coord: IVec2,
+) -> Result<GeneratedChunk, Contradiction> {
+    let origin = coord * CHUNK_SIZE as i32;
+    let roads = road::road_cells(seed, coord);
+
+    let mut chunk = match fit_chunk(rules, seed, coord) {
+        Ok(chunk) => chunk,
+        Err(error) => {
+            warn!("Chunk {coord} does not fit its neighbours, generating it on its own: {error}");
+            let mut solver =
+                wfc::Solver::new(rules.ruleset(), CHUNK_SIZE, CHUNK_SIZE, seed.rng_at(coord));
+            constrain(&mut solver, rules, seed, coord);
+            GeneratedChunk {
+                grid: solver.run()?,
+                fit: ChunkFit::Unconstrained,
+            }
+        }
+    };
+
+    let drivable = |tile| rules.is_drivable(TileType(tile));
+    if !road::corridor_is_open(&chunk.grid, origin, &roads, drivable) {
+        warn!("The road through chunk {coord} is blocked, clearing it");
+        let tile = rules.drivable_tiles().next().ok_or(Contradiction)?;
+        road::repair(&mut chunk.grid, origin, &roads, tile.id());
+    }
+    Ok(chunk)
+}
+
+/// Generates the `width` x `depth` cells whose first cell is `origin`,
+/// stitched together from the chunks that cover them.
+pub fn generate_area(
+    rules: &WFCRules,
+    seed: MapSeed,
+    origin: IVec2,
+    width: usize,
+    depth: usize,
+) -> Result<wfc::Grid, Contradiction> {
+    let mut grid = wfc::Grid::filled(width, depth, 0);
+    let size = IVec2::new(width as i32, depth as i32);
+    let first = origin.div_euclid(IVec2::splat(CHUNK_SIZE as i32));
+    let last = (origin + size - 1).div_euclid(IVec2::splat(CHUNK_SIZE as i32));
+    for z in first.y..=last.y {
+        for x in first.x..=last.x {
+            let coord = IVec2::new(x, z);
+            let chunk = generate_chunk(rules, seed, coord)?;
+            for (x, z, tile) in chunk.grid.iter() {
+                let cell = coord * CHUNK_SIZE as i32 + IVec2::new(x as i32, z as i32) - origin;
+                if cell.cmpge(IVec2::ZERO).all() && cell.cmplt(size).all() {
+                    grid.set(cell.x as usize, cell.y as usize, tile);
+                }
+            }
+        }
+    }
+    Ok(grid)
+}
+
+/// Applies the biomes, roads, slopes and structures around the chunk at `coord` to a
+/// solver whose first cell is the first cell of that chunk.
+///
+/// Road cells may only become drivable tiles and sloped cells tiles that fit slopes.
+/// The cells of a structure may only become its own tiles, regardless of the biome.
+/// Cells next to the solver's border may only become tiles that fit what the cells
+/// beyond the border allow, so that grids solved separately can be joined.
+fn constrain(solver: &mut wfc::Solver<StdRng>, rules: &WFCRules, seed: MapSeed, coord: IVec2) {
+    let origin = coord * CHUNK_SIZE as i32;
+    let roads = road::roads_near(seed, coord);
+    let structures = structure::structures_near(rules, seed, coord);
+    let allowed = |cell: IVec2| -> Vec<usize> {
+        if let Some(tile) = structures.get(&cell) {
+            return vec![tile.id()];
+        }
+        let weights = rules.cell_weights(seed, cell);
+        let slope = elevation::is_slope(rules, seed, cell);
+        (0..rules.tile_count())
+            .filter(|&tile| weights.as_ref().is_none_or(|weights| weights[tile] > 0.0))
+            .filter(|&tile| !roads.contains(&cell) || rules.is_drivable(TileType(tile)))
+            .filter(|&tile| !slope || rules.fits_slope(TileType(tile)))
+            .collect()
+    };
+
+    let size = IVec2::new(solver.width() as i32, solver.depth() as i32);
+    for z in 0..solver.depth() {
+        for x in 0..solver.width() {
+            let local = IVec2::new(x as i32, z as i32);
+            let cell = origin + local;
+            if let Some(tile) = structures.get(&cell) {
+                solver.restrict(x, z, [tile.id()]);
+            } else if let Some(weights) = rules.cell_weights(seed, cell) {
+                solver.set_weights(x, z, weights);
+            }
+            if roads.contains(&cell) {
+                solver.restrict(x, z, rules.drivable_tiles().map(TileType::id));
+            }
+            if elevation::is_slope(rules, seed, cell) {
+                let slope_tiles =
+                    (0..rules.tile_count()).filter(|&tile| rules.fits_slope(TileType(tile)));
+                solver.restrict(x, z, slope_tiles);
+            }
+            for direction in Direction::ALL {
+                let (dx, dz) = direction.offset();
+                let offset = IVec2::new(dx as i32, dz as i32);
+                let outside =
+                    (local + offset).cmplt(IVec2::ZERO).any() || (local + offset).cmpge(size).any();
+                if outside {
+                    solver.restrict_by_neighbour(x, z, direction, allowed(cell + offset));
+                }
+            }
+        }
+    }
+}
+
+/// Backtracking budget for the second attempt at a chunk that did not fit its edges.
+const RETRY_MAX_BACKTRACKS: usize = 16 * 1024;
+
+/// Solves the tiles of the chunk at `coord` so that they fit its neighbours.
+///
+/// The corners and the north and west edges of every chunk are generated first,
+/// each from its own seed, and the interior is then solved with all four edges fixed.
+/// Neighbouring chunks share their edges, so tiles satisfy the rules across chunk
+/// borders regardless of the order in which chunks are loaded. If the interior runs
+/// into a contradiction, it is solved again from another seed with more backtracking.
+fn fit_chunk(
+    rules: &WFCRules,
+    seed: MapSeed,
+    coord: IVec2,
+) -> Result<GeneratedChunk, Contradiction> {
+    let north = edge(rules, seed, coord, Edge::North)?;
+    let south = edge(rules, seed, coord + IVec2::Y, Edge::North)?;
+    let west = edge(rules, seed, coord, Edge::West)?;
+    let east = edge(rules, seed, coord + IVec2::X, Edge::West)?;
+
+    let solve = |mut solver: wfc::Solver<StdRng>| {
+        constrain(&mut solver, rules, seed, coord);
+        for i in 0..=CHUNK_SIZE {
+            solver.fix(i, 0, north[i]);
+            solver.fix(i, CHUNK_SIZE, south[i]);
+            solver.fix(0, i, west[i]);
+            solver.fix(CHUNK_SIZE, i, east[i]);
+        }
+        Ok(solver.run()?.crop(0, 0, CHUNK_SIZE, CHUNK_SIZE))
+    };
+    let size = CHUNK_SIZE + 1;
+    if let Ok(grid) = solve(wfc::Solver::new(
+        rules.ruleset(),
+        size,
+        size,
+        seed.rng_at(coord),
+    )) {
+        return Ok(GeneratedChunk {
+            grid,
+            fit: ChunkFit::Seamless,
+        });
+    }
+    let rng = seam_rng(seed, coord, RETRY_STREAM);
+    let retry = wfc::Solver::new(rules.ruleset(), size, size, rng)
+        .with_max_backtracks(RETRY_MAX_BACKTRACKS);
+    Ok(GeneratedChunk {
+        grid: solve(retry)?,
+        fit: ChunkFit::Retried,
+    })
+}
+
+/// An edge owned by a chunk. The south and east edges belong to the neighbours.
+#[derive(Clone, Copy)]
+enum Edge {
+    North,
+    West,
+}
+
+/// Random stream of [`MapSeed`] used for the corner tile of each chunk.
+const CORNER_STREAM: u64 = 1;
+
+/// Random stream of [`MapSeed`] used to retry a chunk that did not fit its edges.
+/// The other parts of the generator number their streams up from 1, so this one
+/// is kept at the other end.
+const RETRY_STREAM: u64 = u64::MAX;
+
+impl Edge {
+    fn stream(self) -> u64 {
+        match self {
+            Edge::North => 2,
+            Edge::West => 3,
+        }
+    }
+
+    /// Offset from the chunk's own corner to the corner at the other end of this edge.
+    fn direction(self) -> IVec2 {
+        match self {
+            Edge::North => IVec2::X,
+            Edge::West => IVec2::Y,
+        }
+    }
+}
+
+/// The tiles along `edge` of the chunk at `coord`, including the corners at both ends.
+fn edge(
+    rules: &WFCRules,
+    seed: MapSeed,
+    coord: IVec2,
+    edge: Edge,
+) -> Result<Vec<usize>, Contradiction> {
+    let length = edge.direction().as_uvec2() * CHUNK_SIZE as u32 + 1;
+    let (width, depth) = (length.x as usize, length.y as usize);
+
+    let mut solver = wfc::Solver::new(
+        rules.ruleset(),
+        width,
+        depth,
+        seam_rng(seed, coord, edge.stream()),
+    );
+    constrain(&mut solver, rules, seed, coord);
+    solver.fix(0, 0, corner(rules, seed, coord)?);
+    solver.fix(
+        width - 1,
+        depth - 1,
+        corner(rules, seed, coord + edge.direction())?,
+    );
+    Ok(solver.run()?.iter().map(|(_, _, tile)| tile).collect())
+}
+
+/// The tile at the north-west corner of the chunk at `coord`.
+fn corner(rules: &WFCRules, seed: MapSeed, coord: IVec2) -> Result<usize, Contradiction> {
+    let mut solver = wfc::Solver::new(rules.ruleset(), 1, 1, seam_rng(seed, coord, CORNER_STREAM));
+    constrain(&mut solver, rules, seed, coord);
+    Ok(solver.run()?.get(0, 0))
+}
+
+/// A deterministic RNG for the random stream `stream` of the chunk at `coord`.
+pub(crate) fn seam_rng(seed: MapSeed, coord: IVec2, stream: u64) -> StdRng {
+    seed.rng(splitmix64(stream) ^ cell_stream(coord))
+}
@@ -0,0 +1,86 @@
+//! Writes generated grids in formats that can be read without running the game.
+
+use bevy::color::{Color, ColorToPacked};
+use std::io::Write;
+
+use crate::wfc::Grid;
+
+use super::tileset::Tileset;
+
+/// The colour of every tile variant of `tileset`, indexed by [`TileType::id`](super::TileType::id).
+/// Every tile of the tileset has its own hue, and the road tiles are the lightest.
+pub fn tile_colors(tileset: &Tileset) -> Vec<Color> {
+    tileset
+        .variants()
+        .iter()
+        .map(|variant| {
+            let index = tileset
+                .tiles
+                .iter()
+                .position(|tile| tile.id == variant.definition.id)
+                .unwrap_or_default();
+            let hue = (index as f32 * 137.5) % 360.0;
+            let lightness = if variant.definition.drivable {
+                0.8
+            } else {
+                0.4
+            };
+            Color::hsl(hue, 0.5, lightness)
+        })
+        .collect()
+}
+
+/// Draws `grid` as text, one line per row from north to south and one
+/// [`TileDefinition::symbol`](super::tileset::TileDefinition::symbol) per tile.
+pub fn ascii(grid: &Grid, tileset: &Tileset) -> String {
+    let variants = tileset.variants();
+    rows(
+        grid,
+        |tile| variants[tile].definition.symbol().to_string(),
+        "",
+    )
+}
+
+/// Writes `grid` as comma-separated [`TileType::id`](super::TileType::id)s,
+/// one line per row from north to south.
+pub fn csv(grid: &Grid) -> String {
+    rows(grid, |tile| tile.to_string(), ",")
+}
+
+/// Encodes `grid` as a PNG image with `scale` x `scale` pixels per tile.
+/// Tiles are coloured with [`tile_colors`].
+pub fn png(
+    grid: &Grid,
+    tileset: &Tileset,
+    scale: u32,
+    writer: impl Write,
+) -> Result<(), png::EncodingError> {
+    let colors: Vec<[u8; 3]> = tile_colors(tileset)
+        .into_iter()
+        .map(|color| color.to_srgba().to_u8_array_no_alpha())
+        .collect();
+
+    let scale = scale.max(1) as usize;
+    let (width, depth) = (grid.width() * scale, grid.depth() * scale);
+    let mut pixels = Vec::with_capacity(width * depth * 3);
+    for z in 0..depth {
+        for x in 0..width {
+            pixels.extend(colors[grid.get(x / scale, z / scale)]);
+        }
+    }
+
+    let mut encoder = png::Encoder::new(writer, width as u32, depth as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)
+}
+
+fn rows(grid: &Grid, tile: impl Fn(usize) -> String, separator: &str) -> String {
+    let mut text = String::new();
+    for z in 0..grid.depth() {
+        let row: Vec<String> = (0..grid.width()).map(|x| tile(grid.get(x, z))).collect();
+        text.push_str(&row.join(separator));
+        text.push('\n');
+    }
+    text
+}
@@ -0,0 +1,145 @@
+//! The road network that keeps the world drivable.
+//!
+//! Every chunk has a road node somewhere inside it. Each node is linked to the
+//! node of the chunk to its east, so every row of chunks has a road running
+//! through it. Some nodes are also linked to the chunk to their south, forking
+//! off the row roads. Every [`TRUNK_SPACING`]th column of chunks is always
+//! linked, so all rows are connected to each other.
+//!
+//! Links bend at a random point between their nodes. The layout only depends on
+//! the [`MapSeed`], so both chunks a link passes through agree on its cells.
+
+use bevy::math::{IVec2, Vec2Swizzles};
+use rand::Rng;
+use std::collections::{HashSet, VecDeque};
+
+use crate::wfc::Grid;
+
+use super::{seam_rng, MapSeed, CHUNK_SIZE};
+
+/// Random streams of [`MapSeed`] used by the road layout.
+/// They follow the streams used for chunk corners and edges.
+const NODE_STREAM: u64 = 4;
+const EAST_LINK_STREAM: u64 = 5;
+const SOUTH_LINK_STREAM: u64 = 6;
+
+/// Columns of chunks whose nodes are always linked to the south.
+const TRUNK_SPACING: i32 = 4;
+
+/// How likely a node outside of a trunk column forks off to the south.
+const FORK_CHANCE: f64 = 0.35;
+
+/// Every road cell inside the chunk at `coord`, in global cell coordinates.
+pub fn road_cells(seed: MapSeed, coord: IVec2) -> HashSet<IVec2> {
+    let origin = coord * CHUNK_SIZE as i32;
+    let inside = |cell: &IVec2| {
+        let local = *cell - origin;
+        local.cmpge(IVec2::ZERO).all() && local.cmplt(IVec2::splat(CHUNK_SIZE as i32)).all()
+    };
+
+    let mut cells = HashSet::from([node(seed, coord)]);
+    let links = [
+        Some(east_link(seed, coord - IVec2::X)),
+        Some(east_link(seed, coord)),
+        south_link(seed, coord - IVec2::Y),
+        south_link(seed, coord),
+    ];
+    cells.extend(links.into_iter().flatten().flatten().filter(inside));
+    cells
+}
+
+/// Every road cell inside the chunk at `coord` and the eight chunks around it.
+pub fn roads_near(seed: MapSeed, coord: IVec2) -> HashSet<IVec2> {
+    (-1..=1)
+        .flat_map(|x| (-1..=1).map(move |z| IVec2::new(x, z)))
+        .flat_map(|offset| road_cells(seed, coord + offset))
+        .collect()
+}
+
+/// Returns `true` if every road cell of the chunk at `origin` is drivable and can
+/// be reached from every other one without leaving the chunk.
+pub fn corridor_is_open(
+    grid: &Grid,
+    origin: IVec2,
+    roads: &HashSet<IVec2>,
+    drivable: impl Fn(usize) -> bool,
+) -> bool {
+    let drivable_at = |cell: IVec2| {
+        let local = cell - origin;
+        let inside = local.cmpge(IVec2::ZERO).all()
+            && local.x < grid.width() as i32
+            && local.y < grid.depth() as i32;
+        inside && drivable(grid.get(local.x as usize, local.y as usize))
+    };
+    if !roads.iter().all(|&cell| drivable_at(cell)) {
+        return false;
+    }
+    let Some(&start) = roads.iter().next() else {
+        return true;
+    };
+
+    let mut reached = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(cell) = queue.pop_front() {
+        for offset in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+            let next = cell + offset;
+            if drivable_at(next) && reached.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    roads.iter().all(|cell| reached.contains(cell))
+}
+
+/// Clears the road of the chunk at `origin` by replacing every road cell with `tile`.
+/// The replaced cells may no longer fit their neighbours.
+pub fn repair(grid: &mut Grid, origin: IVec2, roads: &HashSet<IVec2>, tile: usize) {
+    for &cell in roads {
+        let local = (cell - origin).as_uvec2();
+        grid.set(local.x as usize, local.y as usize, tile);
+    }
+}
+
+/// The road node of the chunk at `coord`. It keeps a tile of distance to the chunk
+/// border so that links always have room to bend.
+fn node(seed: MapSeed, coord: IVec2) -> IVec2 {
+    let mut rng = seam_rng(seed, coord, NODE_STREAM);
+    let range = 1..CHUNK_SIZE as i32 - 1;
+    coord * CHUNK_SIZE as i32 + IVec2::new(rng.gen_range(range.clone()), rng.gen_range(range))
+}
+
+/// The cells of the road from the node of the chunk at `coord` to the node east of it.
+fn east_link(seed: MapSeed, coord: IVec2) -> Vec<IVec2> {
+    let mut rng = seam_rng(seed, coord, EAST_LINK_STREAM);
+    let from = node(seed, coord);
+    let to = node(seed, coord + IVec2::X);
+    let bend = rng.gen_range(from.x + 1..to.x);
+    bent_line(from, to, bend, IVec2::new)
+}
+
+/// The cells of the road from the node of the chunk at `coord` to the node south of
+/// it, or `None` if the node does not fork off to the south.
+fn south_link(seed: MapSeed, coord: IVec2) -> Option<Vec<IVec2>> {
+    let mut rng = seam_rng(seed, coord, SOUTH_LINK_STREAM);
+    let trunk = coord.x.rem_euclid(TRUNK_SPACING) == 0;
+    if !(rng.gen_bool(FORK_CHANCE) || trunk) {
+        return None;
+    }
+    let from = node(seed, coord).yx();
+    let to = node(seed, coord + IVec2::Y).yx();
+    let bend = rng.gen_range(from.x + 1..to.x);
+    Some(bent_line(from, to, bend, |along, across| {
+        IVec2::new(across, along)
+    }))
+}
+
+/// A continuous line of cells that runs along the first axis from `from` to `bend`,
+/// across to the second coordinate of `to`, and along again until it reaches `to`.
+/// `cell` turns the coordinates along and across the line into a grid cell.
+fn bent_line(from: IVec2, to: IVec2, bend: i32, cell: impl Fn(i32, i32) -> IVec2) -> Vec<IVec2> {
+    let (low, high) = (from.y.min(to.y), from.y.max(to.y));
+    let mut cells: Vec<IVec2> = (from.x..=bend).map(|along| cell(along, from.y)).collect();
+    cells.extend((low..=high).map(|across| cell(bend, across)));
+    cells.extend((bend..=to.x).map(|along| cell(along, to.y)));
+    cells
+}
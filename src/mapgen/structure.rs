@@ -0,0 +1,188 @@
+//! Structures are groups of tiles spanning several cells, such as a walled
+//! courtyard, that are stamped into the map as a unit. The generator then fills
+//! the cells around them so that they fit the sockets along their border.
+//!
+//! Each chunk holds at most one structure. It is kept one cell away from the
+//! border of its chunk, so structures of neighbouring chunks never touch, and
+//! away from roads and slopes unless its tiles there fit them. The placement only
+//! depends on the [`MapSeed`], so every chunk nearby agrees on it.
+
+use bevy::math::IVec2;
+use rand::{seq::SliceRandom, Rng};
+use std::collections::{HashMap, HashSet};
+
+use crate::wfc::Direction;
+
+use super::{
+    biome::biomes_at, elevation, road, seam_rng, tileset::Tileset, MapSeed, TileType, WFCRules,
+    CHUNK_SIZE,
+};
+
+/// Random stream of [`MapSeed`] used to place structures.
+/// It follows the streams used by roads and biomes.
+const STRUCTURE_STREAM: u64 = 8;
+
+/// Number of cells along each side of the largest structure that fits in a chunk.
+pub const MAX_STRUCTURE_SIZE: usize = CHUNK_SIZE - 2;
+
+/// A structure of the loaded [`Tileset`].
+#[derive(Debug, Clone)]
+pub struct Structure {
+    pub id: String,
+    /// The tiles of the structure in every rotation it can be placed in.
+    layouts: Vec<Layout>,
+    chance: f64,
+    /// Indices of the biomes the structure is placed in, empty for every biome.
+    biomes: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Layout {
+    size: IVec2,
+    /// Every tile of the structure, relative to its north-west corner.
+    tiles: Vec<(IVec2, TileType)>,
+}
+
+impl Structure {
+    /// Every valid structure of `tileset`.
+    pub fn from_tileset(tileset: &Tileset) -> Vec<Self> {
+        tileset
+            .structures
+            .iter()
+            .filter_map(|structure| {
+                let layouts = structure.layouts(tileset).ok()?;
+                let layouts = layouts
+                    .into_iter()
+                    .map(|rows| Layout {
+                        size: IVec2::new(rows[0].len() as i32, rows.len() as i32),
+                        tiles: rows
+                            .iter()
+                            .enumerate()
+                            .flat_map(|(z, row)| {
+                                row.iter().enumerate().filter_map(move |(x, &tile)| {
+                                    Some((IVec2::new(x as i32, z as i32), TileType(tile?)))
+                                })
+                            })
+                            .collect(),
+                    })
+                    .collect();
+                let biomes = structure
+                    .biomes
+                    .iter()
+                    .filter_map(|id| tileset.biomes.iter().position(|biome| &biome.id == id))
+                    .collect();
+                Some(Self {
+                    id: structure.id.clone(),
+                    layouts,
+                    chance: f64::from(structure.chance),
+                    biomes,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A structure stamped into the map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    /// Index of the structure in [`WFCRules::structures`].
+    pub structure: usize,
+    /// Every tile of the structure, in global cell coordinates.
+    pub tiles: Vec<(IVec2, TileType)>,
+}
+
+/// The structure placed in the chunk at `coord`, if any.
+pub fn placement(rules: &WFCRules, seed: MapSeed, coord: IVec2) -> Option<Placement> {
+    let mut rng = seam_rng(seed, coord, STRUCTURE_STREAM);
+    let roads = road::road_cells(seed, coord);
+    let origin = coord * CHUNK_SIZE as i32;
+    for (index, structure) in rules.structures().iter().enumerate() {
+        if !rng.gen_bool(structure.chance) {
+            continue;
+        }
+        let layout = structure.layouts.choose(&mut rng)?;
+        let room = IVec2::splat(MAX_STRUCTURE_SIZE as i32) - layout.size;
+        let mut corners: Vec<_> = (0..=room.y)
+            .flat_map(|z| (0..=room.x).map(move |x| origin + IVec2::new(x, z) + 1))
+            .collect();
+        corners.shuffle(&mut rng);
+        let corner = corners
+            .into_iter()
+            .find(|&corner| fits(rules, seed, structure, layout, corner, &roads));
+        if let Some(corner) = corner {
+            return Some(Placement {
+                structure: index,
+                tiles: layout
+                    .tiles
+                    .iter()
+                    .map(|&(offset, tile)| (corner + offset, tile))
+                    .collect(),
+            });
+        }
+    }
+    None
+}
+
+/// The tiles of every structure in the chunk at `coord` and the eight chunks
+/// around it, keyed by global cell coordinates.
+pub fn structures_near(rules: &WFCRules, seed: MapSeed, coord: IVec2) -> HashMap<IVec2, TileType> {
+    if rules.structures().is_empty() {
+        return HashMap::new();
+    }
+    (-1..=1)
+        .flat_map(|x| (-1..=1).map(move |z| IVec2::new(x, z)))
+        .filter_map(|offset| placement(rules, seed, coord + offset))
+        .flat_map(|placement| placement.tiles)
+        .collect()
+}
+
+/// Returns `true` if `layout` can be placed with its north-west corner at `corner`:
+/// it lies in the biomes of the structure, every road it touches stays drivable, and
+/// the tiles it puts on slopes fit them.
+fn fits(
+    rules: &WFCRules,
+    seed: MapSeed,
+    structure: &Structure,
+    layout: &Layout,
+    corner: IVec2,
+    roads: &HashSet<IVec2>,
+) -> bool {
+    if !structure.biomes.is_empty() && !rules.biomes().is_empty() {
+        let in_biome = (0..layout.size.y)
+            .flat_map(|z| (0..layout.size.x).map(move |x| corner + IVec2::new(x, z)))
+            .all(|cell| {
+                biomes_at(seed, cell, rules.biomes().len())
+                    .iter()
+                    .all(|&(biome, influence)| {
+                        influence == 0.0 || structure.biomes.contains(&biome)
+                    })
+            });
+        if !in_biome {
+            return false;
+        }
+    }
+
+    let cells: HashSet<_> = layout
+        .tiles
+        .iter()
+        .map(|&(offset, _)| corner + offset)
+        .collect();
+    layout.tiles.iter().all(|&(offset, tile)| {
+        let cell = corner + offset;
+        if roads.contains(&cell) && !rules.is_drivable(tile) {
+            return false;
+        }
+        if !rules.fits_slope(tile) && elevation::is_slope(rules, seed, cell) {
+            return false;
+        }
+        Direction::ALL.into_iter().all(|direction| {
+            let (dx, dz) = direction.offset();
+            let neighbour = cell + IVec2::new(dx as i32, dz as i32);
+            cells.contains(&neighbour)
+                || !roads.contains(&neighbour)
+                || rules
+                    .drivable_tiles()
+                    .any(|road| rules.allows(tile, direction, road))
+        })
+    })
+}
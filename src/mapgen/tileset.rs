@@ -0,0 +1,524 @@
+//! Tile definitions read from `*.tileset.ron` files.
+
+use bevy::math::Quat;
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::FRAC_PI_2,
+};
+use thiserror::Error;
+
+use super::structure::MAX_STRUCTURE_SIZE;
+use crate::wfc::{Direction, MAX_TILES};
+
+/// Every tile the map generator can place.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tileset {
+    /// Pairs of socket names that fit together. A socket only fits
+    /// a socket with the same name if that pair is listed too.
+    pub connections: Vec<(String, String)>,
+    pub tiles: Vec<TileDefinition>,
+    /// Regions of the world that each use their own mix of tiles.
+    /// Without biomes, every tile is used everywhere with its own weight.
+    #[serde(default)]
+    pub biomes: Vec<BiomeDefinition>,
+    /// Groups of tiles spanning several cells that are placed as a unit.
+    #[serde(default)]
+    pub structures: Vec<StructureDefinition>,
+}
+
+/// A region of the world with its own mix of tiles.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BiomeDefinition {
+    /// Unique name of this biome.
+    pub id: String,
+    /// Colour of the ambient light while riding through this biome.
+    pub ambient: [f32; 3],
+    /// The weight of every tile used in this biome, keyed by tile id.
+    /// These replace the weights of the tiles. Unlisted tiles are not used.
+    pub tiles: HashMap<String, f32>,
+}
+
+/// A group of tiles spanning several cells, such as a walled courtyard, that the
+/// generator places as a unit before filling in the cells around it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StructureDefinition {
+    /// Unique name of this structure.
+    pub id: String,
+    /// The cells of the structure, one string per row from north to south. Each row
+    /// lists its cells from west to east, separated by spaces. A cell is the id of a
+    /// tile, optionally followed by `@` and a number of clockwise quarter turns, or
+    /// `.` for a cell that is generated like any other.
+    ///
+    /// The sockets of the tiles along the border of the footprint are the edge sockets
+    /// of the structure: the cells around it are generated to fit them.
+    pub footprint: Vec<String>,
+    /// How likely a chunk is to hold this structure.
+    pub chance: f32,
+    /// Also place this structure rotated by 90, 180 and 270 degrees.
+    #[serde(default)]
+    pub rotate: bool,
+    /// The biomes this structure is placed in. Without any, it is placed everywhere.
+    #[serde(default)]
+    pub biomes: Vec<String>,
+}
+
+/// The cells of a [`StructureDefinition`] in one of its rotations, as rows from north
+/// to south of indices into [`Tileset::variants`], with `None` for generated cells.
+pub type StructureLayout = Vec<Vec<Option<usize>>>;
+
+impl StructureDefinition {
+    /// The layout of this structure in every rotation it can be placed in.
+    pub fn layouts(&self, tileset: &Tileset) -> Result<Vec<StructureLayout>, TilesetError> {
+        let mut rows = self.parse_footprint()?;
+        let rotations = if self.rotate { 4 } else { 1 };
+        let mut layouts = Vec::with_capacity(rotations);
+        for _ in 0..rotations {
+            let layout = rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|cell| {
+                            cell.map(|(tile, quarter_turns)| {
+                                tileset.variant_index(tile, quarter_turns).ok_or_else(|| {
+                                    TilesetError::UnknownStructureTile {
+                                        structure: self.id.clone(),
+                                        tile: tile.to_string(),
+                                    }
+                                })
+                            })
+                            .transpose()
+                        })
+                        .collect::<Result<_, _>>()
+                })
+                .collect::<Result<_, _>>()?;
+            layouts.push(layout);
+            rows = rotate_clockwise(&rows);
+        }
+        Ok(layouts)
+    }
+
+    /// Splits the footprint into cells of tile ids and quarter turns.
+    fn parse_footprint(&self) -> Result<Vec<Vec<Option<(&str, usize)>>>, TilesetError> {
+        let invalid = || TilesetError::InvalidFootprint(self.id.clone());
+        let rows = self
+            .footprint
+            .iter()
+            .map(|row| {
+                row.split_whitespace()
+                    .map(|cell| match cell.split_once('@') {
+                        _ if cell == "." => Ok(None),
+                        Some((tile, turns)) => turns
+                            .parse()
+                            .map(|turns| Some((tile, turns)))
+                            .map_err(|_| invalid()),
+                        None => Ok(Some((cell, 0))),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let width = rows.first().map_or(0, Vec::len);
+        let rectangle = rows.iter().all(|row| row.len() == width);
+        let fits = (1..=MAX_STRUCTURE_SIZE).contains(&width)
+            && (1..=MAX_STRUCTURE_SIZE).contains(&rows.len());
+        let has_tiles = rows.iter().flatten().any(Option::is_some);
+        if !(rectangle && fits && has_tiles) {
+            return Err(invalid());
+        }
+        Ok(rows)
+    }
+}
+
+/// Turns a footprint a quarter turn clockwise, seen from above, along with its tiles.
+fn rotate_clockwise<'a>(
+    rows: &[Vec<Option<(&'a str, usize)>>],
+) -> Vec<Vec<Option<(&'a str, usize)>>> {
+    let depth = rows.len();
+    let width = rows[0].len();
+    // The west column becomes the north row.
+    (0..width)
+        .map(|z| {
+            (0..depth)
+                .map(|x| rows[depth - 1 - x][z].map(|(tile, turns)| (tile, turns + 1)))
+                .collect()
+        })
+        .collect()
+}
+
+/// A single tile of a [`Tileset`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct TileDefinition {
+    /// Unique name of this tile.
+    pub id: String,
+    /// Path of the scene spawned for this tile.
+    pub model: String,
+    /// Scale of the model. `x` and `z` are multiplied by the tile length,
+    /// so `0.25` fits a model that is 4 units wide into one tile.
+    #[serde(default = "default_scale")]
+    pub scale: [f32; 3],
+    /// How often this tile is picked relative to the other tiles.
+    /// Shared between all rotations of the tile.
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+    /// What each edge of the tile looks like. Two tiles may only sit next
+    /// to each other if the sockets of the touching edges are connected.
+    pub sockets: Sockets,
+    /// Also place this tile rotated by 90, 180 and 270 degrees.
+    #[serde(default)]
+    pub rotate: bool,
+    /// The bike can ride over this tile, so it may be part of a road.
+    #[serde(default)]
+    pub drivable: bool,
+    /// This tile may lie on the slopes between terraces, bent to follow the ground.
+    /// Without any such tile the world is flat.
+    #[serde(default)]
+    pub slope: bool,
+    /// The physics shape of this tile. Tiles without one cannot be touched.
+    #[serde(default)]
+    pub collider: Option<TileCollider>,
+    /// The character drawn for this tile in text maps, such as the ones printed
+    /// by the `mapgen` tool. Defaults to the first character of the id.
+    #[serde(default)]
+    pub symbol: Option<char>,
+}
+
+impl TileDefinition {
+    /// The character drawn for this tile in text maps.
+    pub fn symbol(&self) -> char {
+        self.symbol
+            .or_else(|| self.id.chars().next())
+            .unwrap_or('?')
+    }
+}
+
+/// The physics shape of a tile. Shapes stand on the ground at the center of the
+/// tile and are rotated along with it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum TileCollider {
+    /// The ground, which the bike rides over.
+    Ground,
+    /// A box. Sizes are in the units of the model and are scaled like it.
+    Cuboid {
+        half_extents: [f32; 3],
+        /// Offset of the box from the center of the tile, along x and z.
+        #[serde(default)]
+        offset: [f32; 2],
+    },
+    /// An upright capsule. Sizes are in the units of the model and are scaled like it.
+    Capsule { radius: f32, height: f32 },
+}
+
+impl BiomeDefinition {
+    /// The weight of `variant` in this biome, or zero if the biome does not use it.
+    /// Rotations split the weight of their tile.
+    pub fn weight(&self, variant: &TileVariant) -> f32 {
+        let weight = self
+            .tiles
+            .get(&variant.definition.id)
+            .copied()
+            .unwrap_or(0.0);
+        weight * variant.weight() / variant.definition.weight
+    }
+}
+
+impl TileCollider {
+    /// Everything except the ground blocks the bike.
+    pub fn is_obstacle(&self) -> bool {
+        !matches!(self, TileCollider::Ground)
+    }
+
+    fn is_valid(&self) -> bool {
+        let positive = |value: f32| value.is_finite() && value > 0.0;
+        match self {
+            TileCollider::Ground => true,
+            TileCollider::Cuboid {
+                half_extents,
+                offset,
+            } => {
+                half_extents.iter().all(|&value| positive(value))
+                    && offset.iter().all(|value| value.is_finite())
+            }
+            TileCollider::Capsule { radius, height } => positive(*radius) && positive(*height),
+        }
+    }
+}
+
+/// The socket names of the four edges of a tile.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Sockets {
+    pub north: String,
+    pub east: String,
+    pub south: String,
+    pub west: String,
+}
+
+impl Sockets {
+    /// The socket on side `direction`.
+    pub fn get(&self, direction: Direction) -> &str {
+        match direction {
+            Direction::North => &self.north,
+            Direction::East => &self.east,
+            Direction::South => &self.south,
+            Direction::West => &self.west,
+        }
+    }
+}
+
+fn default_scale() -> [f32; 3] {
+    [0.25, 10.0, 0.25]
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+/// A tile of a [`Tileset`] in one of its rotations.
+#[derive(Debug, Clone)]
+pub struct TileVariant<'a> {
+    pub definition: &'a TileDefinition,
+    /// Number of clockwise quarter turns, seen from above.
+    pub quarter_turns: usize,
+}
+
+impl TileVariant<'_> {
+    /// The socket on side `direction` after rotating.
+    pub fn socket(&self, direction: Direction) -> &str {
+        let unrotated = Direction::ALL[(direction as usize + 4 - self.quarter_turns) % 4];
+        self.definition.sockets.get(unrotated)
+    }
+
+    /// The weight of this variant. Rotations split the weight of their tile.
+    pub fn weight(&self) -> f32 {
+        let rotations = if self.definition.rotate { 4.0 } else { 1.0 };
+        self.definition.weight / rotations
+    }
+
+    /// Rotation of the model, turning clockwise around the y axis.
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_y(-FRAC_PI_2 * self.quarter_turns as f32)
+    }
+}
+
+impl Tileset {
+    /// Parses and validates a tileset written in RON.
+    pub fn from_ron(bytes: &[u8]) -> Result<Self, TilesetError> {
+        let tileset: Tileset = ron::de::from_bytes(bytes)?;
+        tileset.validate()?;
+        Ok(tileset)
+    }
+
+    /// Every tile in every rotation it can be placed in.
+    pub fn variants(&self) -> Vec<TileVariant<'_>> {
+        self.tiles
+            .iter()
+            .flat_map(|definition| {
+                let rotations = if definition.rotate { 4 } else { 1 };
+                (0..rotations).map(move |quarter_turns| TileVariant {
+                    definition,
+                    quarter_turns,
+                })
+            })
+            .collect()
+    }
+
+    /// The index in [`Tileset::variants`] of tile `id` turned by `quarter_turns`.
+    /// Tiles that do not rotate ignore the turns.
+    pub fn variant_index(&self, id: &str, quarter_turns: usize) -> Option<usize> {
+        let mut index = 0;
+        for definition in &self.tiles {
+            let rotations = if definition.rotate { 4 } else { 1 };
+            if definition.id == id {
+                return Some(index + quarter_turns % rotations);
+            }
+            index += rotations;
+        }
+        None
+    }
+
+    /// Returns `true` if sockets `a` and `b` fit together.
+    pub fn connects(&self, a: &str, b: &str) -> bool {
+        self.connections
+            .iter()
+            .any(|(x, y)| (x == a && y == b) || (x == b && y == a))
+    }
+
+    fn validate(&self) -> Result<(), TilesetError> {
+        if self.tiles.is_empty() {
+            return Err(TilesetError::Empty);
+        }
+        if !self.tiles.iter().any(|tile| tile.drivable) {
+            return Err(TilesetError::NoDrivableTile);
+        }
+        let variant_count = self.variants().len();
+        if variant_count > MAX_TILES {
+            return Err(TilesetError::TooManyTiles(variant_count));
+        }
+
+        let mut ids = HashSet::new();
+        for tile in &self.tiles {
+            if !ids.insert(tile.id.as_str()) {
+                return Err(TilesetError::DuplicateTile(tile.id.clone()));
+            }
+            if !(tile.weight.is_finite() && tile.weight > 0.0) {
+                return Err(TilesetError::InvalidWeight(tile.id.clone()));
+            }
+            if tile
+                .collider
+                .as_ref()
+                .is_some_and(|collider| !collider.is_valid())
+            {
+                return Err(TilesetError::InvalidCollider(tile.id.clone()));
+            }
+            for direction in Direction::ALL {
+                let socket = tile.sockets.get(direction);
+                let known = self
+                    .connections
+                    .iter()
+                    .any(|(a, b)| a == socket || b == socket);
+                if !known {
+                    return Err(TilesetError::UnconnectedSocket {
+                        tile: tile.id.clone(),
+                        socket: socket.to_string(),
+                    });
+                }
+            }
+        }
+        self.validate_biomes()?;
+        self.validate_structures()
+    }
+
+    fn validate_biomes(&self) -> Result<(), TilesetError> {
+        let mut ids = HashSet::new();
+        for biome in &self.biomes {
+            if !ids.insert(biome.id.as_str()) {
+                return Err(TilesetError::DuplicateBiome(biome.id.clone()));
+            }
+            for (tile, &weight) in &biome.tiles {
+                if !self.tiles.iter().any(|definition| &definition.id == tile) {
+                    return Err(TilesetError::UnknownBiomeTile {
+                        biome: biome.id.clone(),
+                        tile: tile.clone(),
+                    });
+                }
+                if !(weight.is_finite() && weight > 0.0) {
+                    return Err(TilesetError::InvalidBiomeWeight {
+                        biome: biome.id.clone(),
+                        tile: tile.clone(),
+                    });
+                }
+            }
+            let drivable = self
+                .tiles
+                .iter()
+                .any(|tile| tile.drivable && biome.tiles.contains_key(&tile.id));
+            if !drivable {
+                return Err(TilesetError::NoDrivableBiomeTile(biome.id.clone()));
+            }
+            let slopes = self.tiles.iter().any(|tile| tile.slope);
+            let slope = self
+                .tiles
+                .iter()
+                .any(|tile| tile.slope && biome.tiles.contains_key(&tile.id));
+            if slopes && !slope {
+                return Err(TilesetError::NoSlopeBiomeTile(biome.id.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_structures(&self) -> Result<(), TilesetError> {
+        let variants = self.variants();
+        let mut ids = HashSet::new();
+        for structure in &self.structures {
+            if !ids.insert(structure.id.as_str()) {
+                return Err(TilesetError::DuplicateStructure(structure.id.clone()));
+            }
+            if !(structure.chance > 0.0 && structure.chance <= 1.0) {
+                return Err(TilesetError::InvalidStructureChance(structure.id.clone()));
+            }
+            for biome in &structure.biomes {
+                if !self.biomes.iter().any(|definition| &definition.id == biome) {
+                    return Err(TilesetError::UnknownStructureBiome {
+                        structure: structure.id.clone(),
+                        biome: biome.clone(),
+                    });
+                }
+            }
+            for layout in structure.layouts(self)? {
+                for (z, row) in layout.iter().enumerate() {
+                    for (x, &tile) in row.iter().enumerate() {
+                        let east = row.get(x + 1).copied().flatten();
+                        let south = layout.get(z + 1).and_then(|row| row[x]);
+                        let pairs = [(east, Direction::East), (south, Direction::South)];
+                        for (neighbour, direction) in pairs {
+                            let (Some(a), Some(b)) = (tile, neighbour) else {
+                                continue;
+                            };
+                            let (a, b) = (&variants[a], &variants[b]);
+                            if !self.connects(a.socket(direction), b.socket(direction.opposite())) {
+                                return Err(TilesetError::MismatchedStructureTiles {
+                                    structure: structure.id.clone(),
+                                    a: a.definition.id.clone(),
+                                    b: b.definition.id.clone(),
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TilesetError {
+    #[error("could not read tileset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse tileset: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("tileset has no tiles")]
+    Empty,
+    #[error("tileset has no drivable tile to build roads from")]
+    NoDrivableTile,
+    #[error("tileset has {0} tiles including rotations, but at most {MAX_TILES} are supported")]
+    TooManyTiles(usize),
+    #[error("tile `{0}` is defined more than once")]
+    DuplicateTile(String),
+    #[error("tile `{0}` must have a positive weight")]
+    InvalidWeight(String),
+    #[error("the collider of tile `{0}` must have positive, finite sizes")]
+    InvalidCollider(String),
+    #[error("biome `{0}` is defined more than once")]
+    DuplicateBiome(String),
+    #[error("biome `{biome}` uses tile `{tile}`, which is not defined")]
+    UnknownBiomeTile { biome: String, tile: String },
+    #[error("tile `{tile}` must have a positive weight in biome `{biome}`")]
+    InvalidBiomeWeight { biome: String, tile: String },
+    #[error("biome `{0}` has no drivable tile to build roads from")]
+    NoDrivableBiomeTile(String),
+    #[error("biome `{0}` has no tile that fits slopes, but the world has slopes")]
+    NoSlopeBiomeTile(String),
+    #[error("structure `{0}` is defined more than once")]
+    DuplicateStructure(String),
+    #[error("structure `{0}` must have a chance between 0 and 1")]
+    InvalidStructureChance(String),
+    #[error(
+        "the footprint of structure `{0}` must be a rectangle of `tile`, `tile@turns` or `.` \
+         cells with at least one tile, and at most {MAX_STRUCTURE_SIZE} cells on each side"
+    )]
+    InvalidFootprint(String),
+    #[error("structure `{structure}` uses tile `{tile}`, which is not defined")]
+    UnknownStructureTile { structure: String, tile: String },
+    #[error("structure `{structure}` is placed in biome `{biome}`, which is not defined")]
+    UnknownStructureBiome { structure: String, biome: String },
+    #[error("tiles `{a}` and `{b}` of structure `{structure}` sit next to each other but do not fit together")]
+    MismatchedStructureTiles {
+        structure: String,
+        a: String,
+        b: String,
+    },
+    #[error("socket `{socket}` of tile `{tile}` is not part of any connection")]
+    UnconnectedSocket { tile: String, socket: String },
+}
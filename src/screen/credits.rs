@@ -30,7 +30,9 @@ fn enter_credits(mut commands: Commands) {
             children.label("Cihangir Timur Sefergil");
             children.label("Umay Sefergil");
 
-            children.button("Back").insert(CreditsAction::Back);
+            children
+                .button("Back")
+                .insert((CreditsAction::Back, CancelButton));
         });
 
     commands.trigger(Soundtrack::Credits);
@@ -2,6 +2,7 @@
 
 mod credits;
 mod playing;
+mod settings;
 mod splash;
 mod title;
 
@@ -16,6 +17,7 @@ pub(super) fn plugin(app: &mut App) {
         title::plugin,
         credits::plugin,
         playing::plugin,
+        settings::plugin,
     ));
 }
 
@@ -26,5 +28,6 @@ pub enum Screen {
     Splash,
     Title,
     Credits,
+    Settings,
     Playing,
 }
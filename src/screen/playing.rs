@@ -1,21 +1,53 @@
-//! The screen state for the main game loop.
+//! The screen state for the main game loop, and the pause menu shown over it.
 
-use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy::prelude::*;
 
 use super::Screen;
-use crate::game::{audio::soundtrack::Soundtrack, spawn::level::SpawnLevel};
+use crate::{
+    game::{
+        audio::soundtrack::Soundtrack,
+        input::{action_just_pressed, Action},
+        spawn::level::SpawnLevel,
+    },
+    ui_tools::prelude::*,
+};
 
 pub(super) fn plugin(app: &mut App) {
+    app.add_sub_state::<Pause>();
+    app.enable_state_scoped_entities::<Pause>();
     app.add_systems(OnEnter(Screen::Playing), enter_playing);
     app.add_systems(OnExit(Screen::Playing), exit_playing);
+    app.add_systems(OnEnter(Pause::Paused), enter_pause);
+    app.add_systems(OnExit(Pause::Paused), exit_pause);
 
+    app.register_type::<PauseAction>();
     app.add_systems(
         Update,
-        return_to_title_screen
-            .run_if(in_state(Screen::Playing).and_then(input_just_pressed(KeyCode::Escape))),
+        (
+            toggle_pause.run_if(action_just_pressed(Action::Pause)),
+            handle_pause_action.run_if(in_state(Pause::Paused)),
+        )
+            .chain()
+            .run_if(in_state(Screen::Playing)),
     );
 }
 
+/// Whether the game is paused. The simulation stands still while it is.
+#[derive(SubStates, Debug, Hash, PartialEq, Eq, Clone, Default)]
+#[source(Screen = Screen::Playing)]
+enum Pause {
+    #[default]
+    Running,
+    Paused,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+enum PauseAction {
+    Resume,
+    Quit,
+}
+
 fn enter_playing(mut commands: Commands) {
     commands.trigger(SpawnLevel);
     commands.trigger(Soundtrack::Gameplay);
@@ -33,6 +65,43 @@ fn exit_playing(mut commands: Commands /*mut cameras: Query<&mut Transform, With
     commands.trigger(Soundtrack::Disable);
 }
 
-fn return_to_title_screen(mut next_screen: ResMut<NextState<Screen>>) {
-    next_screen.set(Screen::Title);
+fn toggle_pause(pause: Res<State<Pause>>, mut next_pause: ResMut<NextState<Pause>>) {
+    next_pause.set(match pause.get() {
+        Pause::Running => Pause::Paused,
+        Pause::Paused => Pause::Running,
+    });
+}
+
+fn enter_pause(mut commands: Commands, mut time: ResMut<Time<Virtual>>) {
+    // `FixedUpdate` follows virtual time, so the bike and physics stop with it.
+    time.pause();
+    commands
+        .ui_root()
+        .insert(StateScoped(Pause::Paused))
+        .with_children(|children| {
+            children.label("Paused");
+            children
+                .button("Resume")
+                .insert((PauseAction::Resume, CancelButton));
+            children.button("Quit to Title").insert(PauseAction::Quit);
+        });
+}
+
+fn exit_pause(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn handle_pause_action(
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut next_pause: ResMut<NextState<Pause>>,
+    mut button_query: InteractionQuery<&PauseAction>,
+) {
+    for (interaction, action) in &mut button_query {
+        if matches!(interaction, Interaction::Pressed) {
+            match action {
+                PauseAction::Resume => next_pause.set(Pause::Running),
+                PauseAction::Quit => next_screen.set(Screen::Title),
+            }
+        }
+    }
 }
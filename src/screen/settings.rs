@@ -0,0 +1,137 @@
+//! A settings screen that can be accessed from the title screen, where the keys
+//! and gamepad inputs bound to each [`Action`] can be changed.
+
+use bevy::{prelude::*, ui::Val::*};
+
+use super::Screen;
+use crate::{
+    game::input::{Action, Bindings, Rebinding},
+    ui_tools::prelude::*,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::Settings), enter_settings);
+    app.add_systems(OnExit(Screen::Settings), exit_settings);
+
+    app.register_type::<SettingsAction>();
+    app.add_systems(
+        Update,
+        (
+            handle_settings_action,
+            update_binding_labels
+                .run_if(resource_changed::<Bindings>.or_else(resource_changed::<Rebinding>)),
+            pause_navigation_while_rebinding.run_if(resource_changed::<Rebinding>),
+        )
+            .chain()
+            .run_if(in_state(Screen::Settings)),
+    );
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+enum SettingsAction {
+    /// Waits for an input to bind to the action.
+    Rebind(Action),
+    Reset,
+    Back,
+}
+
+fn enter_settings(mut commands: Commands, bindings: Res<Bindings>) {
+    commands
+        .ui_root()
+        .insert(StateScoped(Screen::Settings))
+        .with_children(|children| {
+            for action in Action::ALL {
+                children
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            column_gap: Px(10.0),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.label(action.name());
+                        row.button(bindings_text(&bindings, action))
+                            .insert(SettingsAction::Rebind(action))
+                            .insert(Style {
+                                width: Px(450.0),
+                                height: Px(65.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            });
+                    });
+            }
+            children.button("Reset").insert(SettingsAction::Reset);
+            children
+                .button("Back")
+                .insert((SettingsAction::Back, CancelButton));
+        });
+}
+
+fn exit_settings(mut rebinding: ResMut<Rebinding>, mut navigation: ResMut<GamepadNavigation>) {
+    rebinding.0 = None;
+    navigation.paused = false;
+}
+
+fn handle_settings_action(
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut bindings: ResMut<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut button_query: InteractionQuery<&SettingsAction>,
+) {
+    for (interaction, action) in &mut button_query {
+        if matches!(interaction, Interaction::Pressed) {
+            match action {
+                SettingsAction::Rebind(action) => rebinding.0 = Some(*action),
+                SettingsAction::Reset => {
+                    *bindings = Bindings::default();
+                    rebinding.0 = None;
+                }
+                SettingsAction::Back => next_screen.set(Screen::Title),
+            }
+        }
+    }
+}
+
+/// Keeps the gamepad from moving between buttons while its buttons are being
+/// bound.
+fn pause_navigation_while_rebinding(
+    rebinding: Res<Rebinding>,
+    mut navigation: ResMut<GamepadNavigation>,
+) {
+    navigation.paused = rebinding.0.is_some();
+}
+
+fn update_binding_labels(
+    bindings: Res<Bindings>,
+    rebinding: Res<Rebinding>,
+    button_query: Query<(&SettingsAction, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (action, children) in &button_query {
+        let SettingsAction::Rebind(action) = *action else {
+            continue;
+        };
+        let label = if rebinding.0 == Some(action) {
+            "Press an input, or Escape or B to cancel...".to_string()
+        } else {
+            bindings_text(&bindings, action)
+        };
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value.clone_from(&label);
+        }
+    }
+}
+
+fn bindings_text(bindings: &Bindings, action: Action) -> String {
+    bindings
+        .get(action)
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
@@ -3,42 +3,81 @@
 use bevy::prelude::*;
 
 use super::Screen;
-use crate::ui_tools::prelude::*;
+use crate::{mapgen::MapSeed, ui_tools::prelude::*};
 
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnEnter(Screen::Title), enter_title);
 
     app.register_type::<TitleAction>();
-    app.add_systems(Update, handle_title_action.run_if(in_state(Screen::Title)));
+    app.register_type::<SeedLabel>();
+    app.add_systems(
+        Update,
+        (
+            handle_title_action,
+            update_seed_label.run_if(resource_changed::<MapSeed>),
+        )
+            .chain()
+            .run_if(in_state(Screen::Title)),
+    );
 }
 
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
 #[reflect(Component)]
 enum TitleAction {
     Play,
+    NewSeed,
+    Settings,
     Credits,
 }
 
-fn enter_title(mut commands: Commands) {
+/// Marks the label showing the current [`MapSeed`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[reflect(Component)]
+struct SeedLabel;
+
+fn enter_title(mut commands: Commands, seed: Res<MapSeed>) {
     commands
         .ui_root()
         .insert(StateScoped(Screen::Title))
         .with_children(|children| {
+            children.label(seed_text(*seed)).insert(SeedLabel);
             children.button("Play").insert(TitleAction::Play);
+            children.button("New Seed").insert(TitleAction::NewSeed);
+            children.button("Settings").insert(TitleAction::Settings);
             children.button("Credits").insert(TitleAction::Credits);
         });
 }
 
 fn handle_title_action(
     mut next_screen: ResMut<NextState<Screen>>,
+    mut seed: ResMut<MapSeed>,
     mut button_query: InteractionQuery<&TitleAction>,
 ) {
     for (interaction, action) in &mut button_query {
         if matches!(interaction, Interaction::Pressed) {
             match action {
                 TitleAction::Play => next_screen.set(Screen::Playing),
+                TitleAction::NewSeed => *seed = MapSeed::default(),
+                TitleAction::Settings => next_screen.set(Screen::Settings),
                 TitleAction::Credits => next_screen.set(Screen::Credits),
             }
         }
     }
 }
+
+fn update_seed_label(
+    seed: Res<MapSeed>,
+    label_query: Query<&Children, With<SeedLabel>>,
+    mut text_query: Query<&mut Text>,
+) {
+    for children in &label_query {
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = seed_text(*seed);
+        }
+    }
+}
+
+fn seed_text(seed: MapSeed) -> String {
+    format!("Seed: {}", seed.0)
+}
@@ -0,0 +1,152 @@
+//! Menu navigation with a gamepad: the D-pad moves the focus between buttons,
+//! South presses the focused button and East presses the [`CancelButton`].
+//!
+//! Pressing a button sets its [`Interaction`] for one frame, so the screens
+//! handle it the same as a click.
+
+use bevy::{prelude::*, ui::UiSystem};
+
+use super::palette::LABEL_TEXT;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<GamepadNavigation>();
+    app.register_type::<GamepadNavigation>();
+    app.register_type::<CancelButton>();
+    app.add_systems(
+        PreUpdate,
+        (release_pressed_button, navigate_with_gamepad)
+            .chain()
+            .after(UiSystem::Focus),
+    );
+}
+
+/// Width of the outline around the focused button.
+const FOCUS_OUTLINE: f32 = 4.0;
+
+/// The button focused by the gamepad, if any.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[reflect(Resource)]
+pub struct GamepadNavigation {
+    /// Ignores the gamepad while `true`, so its buttons can be used for something
+    /// else.
+    pub paused: bool,
+    focused: Option<Entity>,
+    pressed: Option<Entity>,
+}
+
+/// The button pressed by East, to go back from a screen.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[reflect(Component)]
+pub struct CancelButton;
+
+/// Releases the button pressed by the gamepad on the previous frame.
+fn release_pressed_button(
+    mut navigation: ResMut<GamepadNavigation>,
+    mut interactions: Query<&mut Interaction>,
+) {
+    let Some(pressed) = navigation.pressed.take() else {
+        return;
+    };
+    if let Ok(mut interaction) = interactions.get_mut(pressed) {
+        if *interaction == Interaction::Pressed {
+            *interaction = Interaction::None;
+        }
+    }
+}
+
+fn navigate_with_gamepad(
+    mut commands: Commands,
+    mut navigation: ResMut<GamepadNavigation>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut buttons: Query<(Entity, &GlobalTransform, &mut Interaction), With<Button>>,
+    cancel_buttons: Query<Entity, (With<CancelButton>, With<Button>)>,
+) {
+    if navigation.paused {
+        return;
+    }
+    let just_pressed = |button_type| {
+        gamepad_buttons
+            .get_just_pressed()
+            .any(|button| button.button_type == button_type)
+    };
+
+    // The focused button is gone once its screen is left.
+    let focused = navigation
+        .focused
+        .filter(|&focused| buttons.contains(focused));
+    let direction = [
+        (GamepadButtonType::DPadUp, Vec2::NEG_Y),
+        (GamepadButtonType::DPadDown, Vec2::Y),
+        (GamepadButtonType::DPadLeft, Vec2::NEG_X),
+        (GamepadButtonType::DPadRight, Vec2::X),
+    ]
+    .into_iter()
+    .find_map(|(button_type, direction)| just_pressed(button_type).then_some(direction));
+    let next = match (focused, direction) {
+        (None, Some(_)) => first_button(&buttons),
+        (Some(focused), Some(direction)) => {
+            next_button(&buttons, focused, direction).or(Some(focused))
+        }
+        (focused, None) => focused,
+    };
+    if next != navigation.focused {
+        if let Some(mut entity) = navigation.focused.and_then(|e| commands.get_entity(e)) {
+            entity.remove::<Outline>();
+        }
+        if let Some(next) = next {
+            commands.entity(next).insert(Outline::new(
+                Val::Px(FOCUS_OUTLINE),
+                Val::ZERO,
+                LABEL_TEXT,
+            ));
+        }
+        navigation.focused = next;
+    }
+
+    let pressed = if just_pressed(GamepadButtonType::South) {
+        navigation.focused
+    } else if just_pressed(GamepadButtonType::East) {
+        cancel_buttons.iter().next()
+    } else {
+        None
+    };
+    if let Some(pressed) = pressed {
+        if let Ok((_, _, mut interaction)) = buttons.get_mut(pressed) {
+            *interaction = Interaction::Pressed;
+            navigation.pressed = Some(pressed);
+        }
+    }
+}
+
+/// The button at the top left of the screen.
+fn first_button(
+    buttons: &Query<(Entity, &GlobalTransform, &mut Interaction), With<Button>>,
+) -> Option<Entity> {
+    buttons
+        .iter()
+        .map(|(entity, transform, _)| (entity, transform.translation().truncate()))
+        .min_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)))
+        .map(|(entity, _)| entity)
+}
+
+/// The closest button from `focused` towards `direction`, preferring the ones
+/// straight ahead.
+fn next_button(
+    buttons: &Query<(Entity, &GlobalTransform, &mut Interaction), With<Button>>,
+    focused: Entity,
+    direction: Vec2,
+) -> Option<Entity> {
+    let (_, from, _) = buttons.get(focused).ok()?;
+    let from = from.translation().truncate();
+    buttons
+        .iter()
+        .filter(|&(entity, _, _)| entity != focused)
+        .filter_map(|(entity, transform, _)| {
+            let offset = transform.translation().truncate() - from;
+            let ahead = offset.dot(direction);
+            let aside = offset.perp_dot(direction).abs();
+            (ahead > 0.0).then_some((entity, ahead + 2.0 * aside))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, _)| entity)
+}
@@ -0,0 +1,490 @@
+//! A small Wave Function Collapse solver that knows nothing about Bevy.
+//!
+//! Every cell starts in a superposition of all tiles of a [`Ruleset`].
+//! The solver repeatedly collapses the cell with the lowest entropy
+//! to a tile picked according to the tile weights,
+//! propagates the adjacency constraints to its neighbours and, when a cell
+//! runs out of options, backtracks to the most recent decision and tries
+//! another tile there. If backtracking keeps failing, the solver starts
+//! over from scratch with fresh random choices.
+
+use rand::Rng;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Bit set of tile ids. Bit `n` is set if tile `n` is still possible.
+type TileMask = u128;
+
+/// The maximum number of tiles a [`Ruleset`] can hold.
+pub const MAX_TILES: usize = TileMask::BITS as usize;
+
+/// How many times the solver may undo a decision before starting over.
+const DEFAULT_MAX_BACKTRACKS: usize = 1024;
+
+/// How many times the solver may start over before giving up.
+const MAX_ATTEMPTS: usize = 8;
+
+/// One of the four sides of a grid cell.
+/// North points towards negative z, which is Bevy's forward direction.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    /// Grid offset `(dx, dz)` of the neighbour on this side.
+    pub fn offset(self) -> (isize, isize) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::East => (1, 0),
+            Direction::South => (0, 1),
+            Direction::West => (-1, 0),
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Which tiles may sit next to each other, per direction, and how likely each tile is.
+#[derive(Clone, Debug, Default)]
+pub struct Ruleset {
+    /// `compatible[a][d]` holds every tile allowed on side `d` of tile `a`.
+    compatible: Vec<[TileMask; 4]>,
+    /// Relative probability of picking each tile.
+    weights: Vec<f32>,
+}
+
+impl Ruleset {
+    /// Creates a ruleset with `tile_count` equally likely tiles and no allowed adjacencies.
+    pub fn new(tile_count: usize) -> Self {
+        assert!(
+            tile_count <= MAX_TILES,
+            "a ruleset can hold at most {MAX_TILES} tiles, got {tile_count}"
+        );
+        Self {
+            compatible: vec![[0; 4]; tile_count],
+            weights: vec![1.0; tile_count],
+        }
+    }
+
+    /// Sets how likely `tile` is relative to the other tiles. Defaults to `1.0`.
+    pub fn set_weight(&mut self, tile: usize, weight: f32) {
+        assert!(
+            weight.is_finite() && weight > 0.0,
+            "tile weights must be positive, got {weight}"
+        );
+        self.weights[tile] = weight;
+    }
+
+    pub fn weight(&self, tile: usize) -> f32 {
+        self.weights[tile]
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.compatible.len()
+    }
+
+    /// Allows tile `b` on side `direction` of tile `a`.
+    /// The reverse adjacency is allowed as well, so the rules stay symmetric.
+    pub fn allow(&mut self, a: usize, direction: Direction, b: usize) {
+        self.compatible[a][direction.index()] |= 1 << b;
+        self.compatible[b][direction.opposite().index()] |= 1 << a;
+    }
+
+    /// Returns `true` if tile `b` may sit on side `direction` of tile `a`.
+    pub fn is_allowed(&self, a: usize, direction: Direction, b: usize) -> bool {
+        self.compatible[a][direction.index()] & (1 << b) != 0
+    }
+
+    fn all_tiles(&self) -> TileMask {
+        match self.tile_count() {
+            MAX_TILES => TileMask::MAX,
+            count => (1 << count) - 1,
+        }
+    }
+
+    /// Shannon entropy of a cell that can still be any tile in `mask`,
+    /// where each tile is as likely as its entry in `weights`.
+    fn entropy(weights: &[f32], mask: TileMask) -> f32 {
+        let (sum, sum_log) = tiles(mask).fold((0.0, 0.0), |(sum, sum_log), tile| {
+            let weight = weights[tile];
+            (sum + weight, sum_log + weight * weight.ln())
+        });
+        sum.ln() - sum_log / sum
+    }
+
+    /// Every tile that may sit on side `direction` of any tile in `mask`.
+    fn supported_by(&self, mask: TileMask, direction: Direction) -> TileMask {
+        tiles(mask).fold(0, |supported, tile| {
+            supported | self.compatible[tile][direction.index()]
+        })
+    }
+}
+
+/// A fully collapsed grid of tile ids.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Grid {
+    width: usize,
+    depth: usize,
+    tiles: Vec<usize>,
+}
+
+impl Grid {
+    /// A `width` x `depth` grid with `tile` in every cell.
+    pub fn filled(width: usize, depth: usize, tile: usize) -> Grid {
+        Grid {
+            width,
+            depth,
+            tiles: vec![tile; width * depth],
+        }
+    }
+
+    /// Number of cells along the x axis.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of cells along the z axis.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn get(&self, x: usize, z: usize) -> usize {
+        self.tiles[z * self.width + x]
+    }
+
+    /// Replaces the tile at `(x, z)` without checking the rules.
+    pub fn set(&mut self, x: usize, z: usize, tile: usize) {
+        self.tiles[z * self.width + x] = tile;
+    }
+
+    /// Copies the `width` x `depth` cells starting at `(x, z)` into a new grid.
+    pub fn crop(&self, x: usize, z: usize, width: usize, depth: usize) -> Grid {
+        Grid {
+            width,
+            depth,
+            tiles: (z..z + depth)
+                .flat_map(|z| (x..x + width).map(move |x| self.get(x, z)))
+                .collect(),
+        }
+    }
+
+    /// Iterates over `(x, z, tile)` for every cell.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.tiles
+            .iter()
+            .enumerate()
+            .map(|(i, &tile)| (i % self.width, i / self.width, tile))
+    }
+}
+
+/// Returned when the rules cannot be satisfied for the requested grid.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Contradiction;
+
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the tile rules cannot be satisfied for this grid")
+    }
+}
+
+impl std::error::Error for Contradiction {}
+
+/// A collapse the solver may have to undo.
+struct Decision {
+    cell: usize,
+    tile: usize,
+    /// Length of the trail before this decision was applied.
+    trail_len: usize,
+}
+
+/// Solves a single grid. Create one with [`Solver::new`] and call [`Solver::run`].
+pub struct Solver<'a, R: Rng> {
+    rules: &'a Ruleset,
+    width: usize,
+    depth: usize,
+    /// The superposition of every cell.
+    cells: Vec<TileMask>,
+    /// Previous masks of changed cells, used to undo decisions.
+    trail: Vec<(usize, TileMask)>,
+    decisions: Vec<Decision>,
+    /// Cells whose neighbours need to be re-checked.
+    pending: VecDeque<usize>,
+    /// Tile weights of cells that do not use the weights of the ruleset.
+    cell_weights: Vec<Option<Vec<f32>>>,
+    max_backtracks: usize,
+    backtracks_left: usize,
+    rng: R,
+}
+
+impl<'a, R: Rng> Solver<'a, R> {
+    pub fn new(rules: &'a Ruleset, width: usize, depth: usize, rng: R) -> Self {
+        Self {
+            rules,
+            width,
+            depth,
+            cells: vec![rules.all_tiles(); width * depth],
+            trail: Vec::new(),
+            decisions: Vec::new(),
+            pending: VecDeque::new(),
+            cell_weights: vec![None; width * depth],
+            max_backtracks: DEFAULT_MAX_BACKTRACKS,
+            backtracks_left: 0,
+            rng,
+        }
+    }
+
+    /// Number of cells along the x axis.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of cells along the z axis.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Fixes the cell at `(x, z)` to `tile` before solving.
+    /// Use this to continue a grid that was already generated next to this one.
+    pub fn fix(&mut self, x: usize, z: usize, tile: usize) {
+        assert!(tile < self.rules.tile_count(), "unknown tile {tile}");
+        self.cells[z * self.width + x] = 1 << tile;
+    }
+
+    /// Only allows the cell at `(x, z)` to become one of `tiles`.
+    pub fn restrict(&mut self, x: usize, z: usize, tiles: impl IntoIterator<Item = usize>) {
+        let mask = tiles.into_iter().fold(0, |mask: TileMask, tile| {
+            assert!(tile < self.rules.tile_count(), "unknown tile {tile}");
+            mask | 1 << tile
+        });
+        self.cells[z * self.width + x] &= mask;
+    }
+
+    /// Only allows tiles at `(x, z)` that fit next to at least one of `tiles` on side
+    /// `direction`. Use this when that neighbour lies outside of this grid.
+    pub fn restrict_by_neighbour(
+        &mut self,
+        x: usize,
+        z: usize,
+        direction: Direction,
+        tiles: impl IntoIterator<Item = usize>,
+    ) {
+        let neighbour = tiles
+            .into_iter()
+            .fold(0, |mask: TileMask, tile| mask | 1 << tile);
+        self.cells[z * self.width + x] &= self.rules.supported_by(neighbour, direction.opposite());
+    }
+
+    /// Uses `weights` instead of the weights of the ruleset for the cell at `(x, z)`.
+    /// Tiles with a weight of zero are never placed in this cell.
+    pub fn set_weights(&mut self, x: usize, z: usize, weights: Vec<f32>) {
+        assert_eq!(
+            weights.len(),
+            self.rules.tile_count(),
+            "expected a weight for every tile"
+        );
+        let cell = z * self.width + x;
+        for (tile, &weight) in weights.iter().enumerate() {
+            assert!(
+                weight.is_finite() && weight >= 0.0,
+                "tile weights must not be negative, got {weight}"
+            );
+            if weight == 0.0 {
+                self.cells[cell] &= !(1 << tile);
+            }
+        }
+        self.cell_weights[cell] = Some(weights);
+    }
+
+    /// Limits how many decisions may be undone before the solver starts over.
+    pub fn with_max_backtracks(mut self, max_backtracks: usize) -> Self {
+        self.max_backtracks = max_backtracks;
+        self
+    }
+
+    /// Collapses every cell, or fails if the rules cannot be satisfied.
+    pub fn run(mut self) -> Result<Grid, Contradiction> {
+        if self.cells.contains(&0) {
+            return Err(Contradiction);
+        }
+
+        let initial = self.cells.clone();
+        for _ in 0..MAX_ATTEMPTS {
+            if self.attempt() {
+                return Ok(Grid {
+                    width: self.width,
+                    depth: self.depth,
+                    tiles: self
+                        .cells
+                        .iter()
+                        .map(|&mask| mask.trailing_zeros() as usize)
+                        .collect(),
+                });
+            }
+            self.cells.clone_from(&initial);
+            self.trail.clear();
+            self.decisions.clear();
+        }
+        Err(Contradiction)
+    }
+
+    /// Tries to collapse every cell within the backtracking budget.
+    fn attempt(&mut self) -> bool {
+        self.backtracks_left = self.max_backtracks;
+        self.pending.extend(0..self.cells.len());
+
+        loop {
+            if !self.propagate() {
+                if !self.backtrack() {
+                    return false;
+                }
+                continue;
+            }
+
+            let Some(cell) = self.lowest_entropy_cell() else {
+                return true;
+            };
+            let tile = self.random_tile(cell);
+            self.decisions.push(Decision {
+                cell,
+                tile,
+                trail_len: self.trail.len(),
+            });
+            self.set(cell, 1 << tile);
+        }
+    }
+
+    /// Narrows a cell down to `mask` and schedules its neighbours for propagation.
+    fn set(&mut self, cell: usize, mask: TileMask) {
+        self.trail.push((cell, self.cells[cell]));
+        self.cells[cell] = mask;
+        self.pending.push_back(cell);
+    }
+
+    /// Removes tiles that are no longer supported by their neighbours.
+    /// Returns `false` if a cell ends up with no possible tile.
+    fn propagate(&mut self) -> bool {
+        while let Some(cell) = self.pending.pop_front() {
+            let mask = self.cells[cell];
+            for direction in Direction::ALL {
+                let Some(neighbour) = self.neighbour(cell, direction) else {
+                    continue;
+                };
+                let current = self.cells[neighbour];
+                let next = current & self.rules.supported_by(mask, direction);
+                if next == current {
+                    continue;
+                }
+                if next == 0 {
+                    self.pending.clear();
+                    return false;
+                }
+                self.set(neighbour, next);
+            }
+        }
+        true
+    }
+
+    /// Undoes decisions until one can be retried with a different tile.
+    /// Returns `false` if there is nothing left to try.
+    fn backtrack(&mut self) -> bool {
+        while let Some(decision) = self.decisions.pop() {
+            if self.backtracks_left == 0 {
+                return false;
+            }
+            self.backtracks_left -= 1;
+
+            for (cell, mask) in self.trail.drain(decision.trail_len..).rev() {
+                self.cells[cell] = mask;
+            }
+
+            let remaining = self.cells[decision.cell] & !(1 << decision.tile);
+            if remaining != 0 {
+                self.set(decision.cell, remaining);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Picks the undecided cell with the lowest entropy, breaking ties randomly.
+    fn lowest_entropy_cell(&mut self) -> Option<usize> {
+        let mut best = None;
+        let mut best_entropy = f32::INFINITY;
+        for (cell, &mask) in self.cells.iter().enumerate() {
+            if mask.count_ones() <= 1 {
+                continue;
+            }
+            let noise = self.rng.gen::<f32>() * 1e-3;
+            let entropy = Ruleset::entropy(self.weights(cell), mask) + noise;
+            if entropy < best_entropy {
+                best_entropy = entropy;
+                best = Some(cell);
+            }
+        }
+        best
+    }
+
+    /// The tile weights of `cell`.
+    fn weights(&self, cell: usize) -> &[f32] {
+        self.cell_weights[cell]
+            .as_deref()
+            .unwrap_or(&self.rules.weights)
+    }
+
+    /// Picks one of the tiles still possible in `cell`, proportionally to their weights.
+    fn random_tile(&mut self, cell: usize) -> usize {
+        let mask = self.cells[cell];
+        let total: f32 = tiles(mask).map(|tile| self.weights(cell)[tile]).sum();
+        let mut pick = self.rng.gen_range(0.0..total);
+        for tile in tiles(mask) {
+            let weight = self.weights(cell)[tile];
+            if pick < weight {
+                return tile;
+            }
+            pick -= weight;
+        }
+        // Rounding errors can leave `pick` just above the last weight.
+        (TileMask::BITS - 1 - mask.leading_zeros()) as usize
+    }
+
+    fn neighbour(&self, cell: usize, direction: Direction) -> Option<usize> {
+        let (dx, dz) = direction.offset();
+        let x = (cell % self.width).checked_add_signed(dx)?;
+        let z = (cell / self.width).checked_add_signed(dz)?;
+        (x < self.width && z < self.depth).then_some(z * self.width + x)
+    }
+}
+
+/// Iterates over the tile ids set in `mask`.
+fn tiles(mut mask: TileMask) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let tile = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        Some(tile)
+    })
+}
@@ -0,0 +1,227 @@
+use bevy::{input::InputPlugin, prelude::*, scene::ScenePlugin, time::TimeUpdateStrategy};
+use bevy_rapier3d::prelude::*;
+use bevy_template::game::{
+    input::{self, BindingsFile},
+    movement::{self, Bike, BikeInput, BikeTuning, VerticalVelocity},
+    spawn::{level, player::Player},
+    SIMULATION_HZ,
+};
+use std::time::Duration;
+
+const DELTA: f32 = 1.0 / 60.0;
+
+fn full_throttle() -> BikeInput {
+    BikeInput {
+        throttle: 1.0,
+        ..default()
+    }
+}
+
+/// Steps `bike` for `seconds` of `input`.
+fn hold(bike: &mut Bike, input: BikeInput, seconds: f32) {
+    let tuning = BikeTuning::default();
+    for _ in 0..(seconds / DELTA) as usize {
+        bike.step(&tuning, input, DELTA);
+    }
+}
+
+#[test]
+fn throttle_speeds_up_to_the_top_speed() {
+    let tuning = BikeTuning::default();
+    let mut bike = Bike::default();
+    hold(&mut bike, full_throttle(), 1.0);
+    let after_a_second = bike.speed;
+    assert!(after_a_second > 0.0);
+
+    hold(&mut bike, full_throttle(), 1.0);
+    let gained = bike.speed - after_a_second;
+    assert!(
+        gained < after_a_second,
+        "the bike should speed up slower as it gets faster"
+    );
+
+    hold(&mut bike, full_throttle(), 60.0);
+    assert!(bike.speed <= tuning.top_speed);
+    assert!(bike.speed > tuning.top_speed * 0.95, "{}", bike.speed);
+}
+
+#[test]
+fn braking_stops_sooner_than_coasting() {
+    let time_to_stop = |input: BikeInput| {
+        let mut bike = Bike {
+            speed: 30.0,
+            ..default()
+        };
+        let mut frames = 0;
+        while bike.speed > 0.0 {
+            bike.step(&BikeTuning::default(), input, DELTA);
+            frames += 1;
+            assert!(bike.speed >= 0.0, "stopping turned the bike around");
+        }
+        frames
+    };
+    let braking = BikeInput {
+        brake: 1.0,
+        ..default()
+    };
+    assert!(time_to_stop(braking) < time_to_stop(BikeInput::default()));
+}
+
+#[test]
+fn holding_the_brake_when_stopped_rides_backwards() {
+    let tuning = BikeTuning::default();
+    let mut bike = Bike::default();
+    let reverse = BikeInput {
+        brake: 1.0,
+        ..default()
+    };
+    hold(&mut bike, reverse, 10.0);
+    assert!(bike.speed < 0.0);
+    assert!(bike.speed >= -tuning.reverse_speed);
+}
+
+#[test]
+fn steering_turns_the_heading_only_when_moving() {
+    let right = BikeInput {
+        steer: 1.0,
+        ..default()
+    };
+    let mut bike = Bike::default();
+    hold(&mut bike, right, 1.0);
+    assert_eq!(bike.heading, 0.0);
+
+    let mut bike = Bike {
+        speed: 20.0,
+        ..default()
+    };
+    hold(
+        &mut bike,
+        BikeInput {
+            throttle: 1.0,
+            ..right
+        },
+        0.5,
+    );
+    assert!(bike.forward().x > 0.1, "{:?}", bike.forward());
+}
+
+fn app() -> App {
+    app_at(60)
+}
+
+/// An app drawing `fps` frames per second.
+fn app_at(fps: u32) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        AssetPlugin::default(),
+        InputPlugin,
+        ScenePlugin,
+        level::physics_plugin,
+        input::plugin,
+        movement::plugin,
+    ))
+    .init_asset::<Mesh>()
+    .insert_resource(BindingsFile(None))
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        1.0 / f64::from(fps),
+    )));
+    app.world_mut().spawn((
+        Collider::cuboid(100.0, 0.5, 100.0),
+        TransformBundle::from_transform(Transform::from_xyz(0.0, -0.5, 0.0)),
+    ));
+    app.world_mut().spawn((
+        Player,
+        Bike::default(),
+        BikeInput::default(),
+        VerticalVelocity::default(),
+        TransformBundle::from_transform(Transform::from_xyz(0.0, 1.0, 0.0)),
+        RigidBody::KinematicPositionBased,
+        Collider::cuboid(0.5, 1.0, 0.5),
+        KinematicCharacterController::default(),
+    ));
+    // Time only starts passing from the second frame.
+    app.update();
+    app
+}
+
+fn player(app: &mut App) -> (Bike, Vec3) {
+    let mut player = app
+        .world_mut()
+        .query_filtered::<(&Bike, &Transform), With<Player>>();
+    let (bike, transform) = player.single(app.world());
+    (*bike, transform.translation)
+}
+
+#[test]
+fn the_throttle_key_rides_the_bike_forward() {
+    let mut app = app();
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::KeyW);
+    for _ in 0..60 {
+        app.update();
+    }
+    let (bike, position) = player(&mut app);
+    assert!(bike.speed > 0.0);
+    assert!(position.z < -1.0, "{position}");
+}
+
+#[test]
+fn obstacles_stop_the_bike() {
+    let mut app = app();
+    app.world_mut().spawn((
+        Collider::cuboid(5.0, 2.0, 0.5),
+        TransformBundle::from_transform(Transform::from_xyz(0.0, 1.0, -5.0)),
+    ));
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::KeyW);
+    for _ in 0..180 {
+        app.update();
+    }
+    let (bike, position) = player(&mut app);
+    assert!(position.z > -4.5, "the bike rode through the wall");
+    assert!(bike.speed < 1.0, "the bike kept its speed: {}", bike.speed);
+}
+
+#[test]
+fn the_bike_rides_the_same_at_any_frame_rate() {
+    let ride = |fps: u32| {
+        let mut app = app_at(fps);
+        let inputs: [&[KeyCode]; 3] = [
+            &[KeyCode::KeyW],
+            &[KeyCode::KeyW, KeyCode::KeyD],
+            &[KeyCode::KeyS, KeyCode::KeyA],
+        ];
+        for keys in inputs {
+            let mut input = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            input.release_all();
+            for &key in keys {
+                input.press(key);
+            }
+            // Hold the keys for a second.
+            for _ in 0..fps {
+                app.update();
+            }
+        }
+        player(&mut app)
+    };
+    let (slow, fast) = (ride(30), ride(144));
+    assert!(slow.0.speed != 0.0 && slow.0.heading != 0.0);
+    assert_eq!(slow, fast);
+}
+
+#[test]
+fn the_physics_steps_with_the_simulation() {
+    let app = app();
+    let timestep = app.world().resource::<Time<Fixed>>().timestep();
+    assert_eq!(timestep, Duration::from_secs_f64(1.0 / SIMULATION_HZ));
+    let config = app.world().resource::<RapierConfiguration>();
+    let TimestepMode::Fixed { dt, .. } = config.timestep_mode else {
+        panic!("the physics does not use a fixed timestep");
+    };
+    assert_eq!(dt, timestep.as_secs_f32());
+}
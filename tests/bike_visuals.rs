@@ -0,0 +1,125 @@
+use bevy::{prelude::*, render::primitives::Aabb, time::TimeUpdateStrategy};
+use bevy_template::game::{
+    bike_visuals::{self, BikeBody, BikeVisuals},
+    movement::{Bike, BikeInput, BikeTuning},
+};
+use std::time::Duration;
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, bike_visuals::plugin))
+        .init_resource::<BikeTuning>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+            1.0 / 60.0,
+        )));
+    app
+}
+
+/// Spawns a bike riding at `speed` with `input`, and returns its body.
+fn spawn_bike(app: &mut App, speed: f32, input: BikeInput) -> (Entity, Entity) {
+    let mut body = Entity::PLACEHOLDER;
+    let bike = app
+        .world_mut()
+        .spawn((
+            Bike { speed, ..default() },
+            input,
+            BikeVisuals::default(),
+            Transform::IDENTITY,
+        ))
+        .with_children(|bike| {
+            body = bike.spawn((BikeBody, Transform::IDENTITY)).id();
+        })
+        .id();
+    (bike, body)
+}
+
+fn run(app: &mut App, seconds: f32) {
+    for _ in 0..(seconds * 60.0) as usize {
+        app.update();
+    }
+}
+
+#[test]
+fn the_body_leans_into_turns_and_back() {
+    let mut app = app();
+    let left = BikeInput {
+        steer: -1.0,
+        ..default()
+    };
+    let (bike, body) = spawn_bike(&mut app, 30.0, left);
+    run(&mut app, 1.0);
+    let up = app.world().get::<Transform>(body).unwrap().rotation * Vec3::Y;
+    assert!(up.x < -0.2, "the body should lean left: {up}");
+    assert_eq!(
+        app.world().get::<Transform>(bike).unwrap().rotation,
+        Quat::IDENTITY,
+        "the bike itself should stay upright"
+    );
+
+    *app.world_mut().get_mut::<BikeInput>(bike).unwrap() = BikeInput::default();
+    run(&mut app, 2.0);
+    let lean = app.world().get::<BikeVisuals>(bike).unwrap().lean;
+    assert!(lean.abs() < 0.01, "the body should stand back up: {lean}");
+}
+
+#[test]
+fn stopped_bikes_do_not_lean() {
+    let mut app = app();
+    let right = BikeInput {
+        steer: 1.0,
+        ..default()
+    };
+    let (bike, _) = spawn_bike(&mut app, 0.0, right);
+    run(&mut app, 1.0);
+    assert_eq!(app.world().get::<BikeVisuals>(bike).unwrap().lean, 0.0);
+}
+
+/// Spawns a wheel of radius 0.5 around `center` on `body`, the way the model's
+/// scene would.
+fn spawn_wheel(app: &mut App, body: Entity, name: &str, center: Vec3) -> Entity {
+    app.world_mut()
+        .spawn((Name::new(name.to_string()), Transform::IDENTITY))
+        .set_parent(body)
+        .with_children(|wheel| {
+            wheel.spawn((
+                Aabb::from_min_max(Vec3::splat(-0.5), Vec3::splat(0.5)),
+                Transform::from_translation(center),
+            ));
+        })
+        .id()
+}
+
+#[test]
+fn wheels_roll_forward() {
+    let mut app = app();
+    let (_, body) = spawn_bike(&mut app, 10.0, BikeInput::default());
+    let wheel = spawn_wheel(&mut app, body, "front_wheel_66", Vec3::ZERO);
+    run(&mut app, 0.1);
+    let top = app.world().get::<Transform>(wheel).unwrap().rotation * Vec3::Y;
+    assert!(
+        top.z < 0.0,
+        "the top of the wheel should move forward: {top}"
+    );
+}
+
+#[test]
+fn each_wheel_spins_around_its_own_axle() {
+    let mut app = app();
+    let (_, body) = spawn_bike(&mut app, 10.0, BikeInput::default());
+    let front = Vec3::new(0.0, 0.5, -2.0);
+    let rear = Vec3::new(0.0, 0.5, 2.0);
+    let wheels = [
+        (spawn_wheel(&mut app, body, "front_wheel", front), front),
+        (spawn_wheel(&mut app, body, "rear_wheel", rear), rear),
+    ];
+    run(&mut app, 0.5);
+    for (wheel, center) in wheels {
+        let transform = app.world().get::<Transform>(wheel).unwrap();
+        assert_ne!(transform.rotation, Quat::IDENTITY, "the wheel should spin");
+        let moved = transform.transform_point(center);
+        assert!(
+            moved.distance(center) < 1e-4,
+            "the axle should stay in place: {center} moved to {moved}"
+        );
+    }
+}
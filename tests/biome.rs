@@ -0,0 +1,66 @@
+mod common;
+
+use bevy::math::IVec2;
+use bevy_template::mapgen::{
+    biome::biomes_at, generate_chunk, structure::placement, MapSeed, CHUNK_SIZE,
+};
+use common::default_rules;
+
+/// The biomes with any influence on `cell`.
+fn biomes(seed: MapSeed, cell: IVec2) -> Vec<usize> {
+    biomes_at(seed, cell, 3)
+        .into_iter()
+        .filter(|&(_, influence)| influence > 0.0)
+        .map(|(biome, _)| biome)
+        .collect()
+}
+
+#[test]
+fn biomes_cover_the_world_and_blend_at_their_borders() {
+    let seed = MapSeed(11);
+    let mut seen = [false; 3];
+    for x in -300..300 {
+        for z in -300..300 {
+            let cell = IVec2::new(x, z);
+            let [(a, a_influence), (_, b_influence)] = biomes_at(seed, cell, 3);
+            assert!((a_influence + b_influence - 1.0).abs() < 1e-6);
+            seen[a] = true;
+
+            // Neighbouring cells always share a biome, so there are no hard borders.
+            let here = biomes(seed, cell);
+            for neighbour in [cell + IVec2::X, cell + IVec2::Y] {
+                assert!(
+                    biomes(seed, neighbour)
+                        .iter()
+                        .any(|biome| here.contains(biome)),
+                    "hard biome border between {cell} and {neighbour}"
+                );
+            }
+        }
+    }
+    assert_eq!(seen, [true; 3]);
+}
+
+#[test]
+fn tiles_belong_to_the_biomes_around_them() {
+    let rules = default_rules();
+    let seed = MapSeed(5);
+    for x in -4..4 {
+        for z in -4..4 {
+            let coord = IVec2::new(x, z);
+            let grid = generate_chunk(&rules, seed, coord).unwrap().grid;
+            // Structures bring their own tiles into any biome they are placed in.
+            let structure = placement(&rules, seed, coord).map_or(vec![], |placement| {
+                placement.tiles.into_iter().map(|(cell, _)| cell).collect()
+            });
+            for (x, z, tile) in grid.iter() {
+                let cell = coord * CHUNK_SIZE as i32 + IVec2::new(x as i32, z as i32);
+                if structure.contains(&cell) {
+                    continue;
+                }
+                let weights = rules.cell_weights(seed, cell).unwrap();
+                assert!(weights[tile] > 0.0, "tile {tile} does not belong at {cell}");
+            }
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use bevy_template::game::{
+    lod::{Lod, TileLod},
+    map_change::{
+        self, Block, Chunk, ChunkTiles, Kare, KareError, LoadedChunks, PendingChunks,
+        TILE_SPAWN_BUDGET,
+    },
+    spawn::player::Player,
+    terrain::LEVEL_RISE,
+    tileset,
+};
+use bevy_template::mapgen::{
+    elevation, generate_chunk, tileset::Tileset, ChunkFit, MapSeed, TileType, WFCRules, CHUNK_SIZE,
+};
+use bevy_template::wfc::Direction;
+use std::collections::{HashMap, HashSet};
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .init_asset::<Scene>()
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .add_plugins((tileset::plugin, map_change::plugin))
+        .insert_resource(Kare::new(20, 4).unwrap())
+        // Draw every chunk in full, so that every tile has a `Block`.
+        .insert_resource(TileLod {
+            near: 2.0,
+            mid: 2.0,
+            hysteresis: 0.0,
+        })
+        .insert_resource(MapSeed(7));
+    app.world_mut().spawn((Player, Transform::default()));
+
+    // Wait for the tileset to load so it is not applied in the middle of a test.
+    for _ in 0..1000 {
+        if app.world().resource::<WFCRules>().tile_count() > 0 {
+            return app;
+        }
+        app.update();
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    panic!("the tileset did not load");
+}
+
+/// Moves the player and waits until every chunk in view has been spawned.
+fn move_player(app: &mut App, translation: Vec3) {
+    place_player(app, translation);
+    for _ in 0..10_000 {
+        app.update();
+        if app.world().resource::<PendingChunks>().is_empty() {
+            return;
+        }
+        std::thread::yield_now();
+    }
+    panic!("the chunks around {translation} were not generated");
+}
+
+fn place_player(app: &mut App, translation: Vec3) {
+    let mut player = app
+        .world_mut()
+        .query_filtered::<&mut Transform, With<Player>>();
+    player.single_mut(app.world_mut()).translation = translation;
+}
+
+/// Every loaded tile, keyed by its cell. Panics if two tiles share a cell.
+fn tiles(app: &mut App) -> HashMap<IVec2, i32> {
+    let mut tiles = HashMap::new();
+    let mut blocks = app.world_mut().query::<&Block>();
+    for block in blocks.iter(app.world()) {
+        let previous = tiles.insert(block.cell, block.id);
+        assert!(previous.is_none(), "two tiles in cell {}", block.cell);
+    }
+    tiles
+}
+
+#[test]
+fn no_two_blocks_share_a_cell() {
+    let mut app = app();
+    for step in 0..200 {
+        let angle = step as f32 * 0.05;
+        move_player(
+            &mut app,
+            Vec3::new(angle.cos() * step as f32, 0.0, angle.sin() * step as f32),
+        );
+        tiles(&mut app);
+    }
+}
+
+#[test]
+fn revisited_chunks_are_regenerated_identically() {
+    let mut app = app();
+    move_player(&mut app, Vec3::ZERO);
+    let before = tiles(&mut app);
+    assert!(!before.is_empty());
+
+    move_player(&mut app, Vec3::new(1000.0, 0.0, -1000.0));
+    let far_away = tiles(&mut app);
+    assert!(far_away.keys().all(|cell| !before.contains_key(cell)));
+
+    move_player(&mut app, Vec3::ZERO);
+    assert_eq!(tiles(&mut app), before);
+}
+
+#[test]
+fn chunks_are_spawned_once() {
+    let mut app = app();
+    move_player(&mut app, Vec3::ZERO);
+    let loaded = app.world().resource::<LoadedChunks>().len();
+    let count = tiles(&mut app).len();
+    for _ in 0..10 {
+        move_player(&mut app, Vec3::new(0.5, 0.0, 0.5));
+    }
+    assert_eq!(app.world().resource::<LoadedChunks>().len(), loaded);
+    assert_eq!(tiles(&mut app).len(), count);
+}
+
+#[test]
+fn rules_hold_across_chunk_borders() {
+    let mut app = app();
+    for position in [Vec3::ZERO, Vec3::new(-70.0, 0.0, 45.0)] {
+        move_player(&mut app, position);
+        let tiles = tiles(&mut app);
+        let rules = app.world().resource::<WFCRules>();
+        for (&cell, &id) in &tiles {
+            for (direction, offset) in [(Direction::East, IVec2::X), (Direction::South, IVec2::Y)] {
+                let neighbour = cell + offset;
+                if let Some(&other) = tiles.get(&neighbour) {
+                    assert!(
+                        rules.allows(
+                            TileType::from_id(id as usize),
+                            direction,
+                            TileType::from_id(other as usize)
+                        ),
+                        "{cell} and {neighbour} do not fit"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn loaded_chunks_fit_their_neighbours() {
+    let mut app = app();
+    move_player(&mut app, Vec3::ZERO);
+    let mut chunks = app.world_mut().query::<&Chunk>();
+    for chunk in chunks.iter(app.world()) {
+        assert_ne!(chunk.fit, ChunkFit::Unconstrained, "chunk {}", chunk.coord);
+    }
+}
+
+#[test]
+fn chunks_that_cannot_fit_are_flagged() {
+    // Two tiles that never touch each other, so an edge between unlike corners fails.
+    let tileset = Tileset::from_ron(
+        br#"(
+            connections: [("a", "a"), ("b", "b")],
+            tiles: [
+                (id: "a", model: "a.glb#Scene0", sockets: (north: "a", east: "a", south: "a", west: "a"), drivable: true),
+                (id: "b", model: "b.glb#Scene0", sockets: (north: "b", east: "b", south: "b", west: "b"), drivable: true),
+            ],
+        )"#,
+    )
+    .unwrap();
+    let rules = WFCRules::from_tileset(&tileset);
+    let fits: Vec<ChunkFit> = (0..16)
+        .map(|x| {
+            generate_chunk(&rules, MapSeed(3), IVec2::new(x, 0))
+                .unwrap()
+                .fit
+        })
+        .collect();
+    assert!(fits.contains(&ChunkFit::Unconstrained));
+}
+
+#[test]
+fn chunks_are_spawned_over_several_frames() {
+    let mut app = app();
+    place_player(&mut app, Vec3::ZERO);
+    let mut frames = 0;
+    // Chunks may already have been spawned while the tileset was loading.
+    let mut previous = app.world().resource::<LoadedChunks>().len();
+    loop {
+        app.update();
+        let loaded = app.world().resource::<LoadedChunks>().len();
+        let spawned_tiles = (loaded - previous) * CHUNK_SIZE * CHUNK_SIZE;
+        assert!(spawned_tiles <= TILE_SPAWN_BUDGET.max(CHUNK_SIZE * CHUNK_SIZE));
+        previous = loaded;
+        frames += 1;
+        if app.world().resource::<PendingChunks>().is_empty() {
+            break;
+        }
+        std::thread::yield_now();
+    }
+    assert!(frames > 1);
+    assert!(previous * CHUNK_SIZE * CHUNK_SIZE > TILE_SPAWN_BUDGET);
+}
+
+#[test]
+fn invalid_settings_are_rejected() {
+    assert_eq!(Kare::new(0, 4), Err(KareError::TilesInView(0)));
+    assert_eq!(Kare::new(20, 0), Err(KareError::TileLength(0)));
+    assert_eq!(Kare::new(20, 1000), Err(KareError::TileLength(1000)));
+    assert!(Kare::default().is_valid());
+}
+
+#[test]
+fn invalid_settings_pause_the_map() {
+    let mut app = app();
+    *app.world_mut().resource_mut::<Kare>() = Kare {
+        bir_siradaki_kare_sayisi: 0,
+        kare_kenar_uzunlugu: 0,
+    };
+    let far_away = Vec3::new(1000.0, 0.0, 0.0);
+    place_player(&mut app, far_away);
+    for _ in 0..10 {
+        app.update();
+    }
+    let coord = IVec2::new(1000 / 4 / CHUNK_SIZE as i32, 0);
+    assert!(app.world().resource::<LoadedChunks>().get(coord).is_none());
+
+    *app.world_mut().resource_mut::<Kare>() = Kare::new(20, 4).unwrap();
+    move_player(&mut app, far_away);
+    assert!(app.world().resource::<LoadedChunks>().get(coord).is_some());
+}
+
+#[test]
+fn changing_the_seed_rebuilds_the_map() {
+    let mut app = app();
+    move_player(&mut app, Vec3::ZERO);
+    let before = tiles(&mut app);
+
+    app.insert_resource(MapSeed(8));
+    move_player(&mut app, Vec3::ZERO);
+    let after = tiles(&mut app);
+    assert_eq!(
+        before.keys().collect::<HashSet<_>>(),
+        after.keys().collect()
+    );
+    assert_ne!(before, after);
+}
+
+#[test]
+fn changing_the_tile_length_rebuilds_the_map() {
+    let mut app = app();
+    move_player(&mut app, Vec3::ZERO);
+    let chunk = app.world().resource::<LoadedChunks>().get(IVec2::ZERO);
+
+    // Only the view changes, so the chunks that stay in view are kept.
+    app.insert_resource(Kare::new(30, 4).unwrap());
+    move_player(&mut app, Vec3::ZERO);
+    assert_eq!(
+        app.world().resource::<LoadedChunks>().get(IVec2::ZERO),
+        chunk
+    );
+
+    app.insert_resource(Kare::new(30, 6).unwrap());
+    move_player(&mut app, Vec3::ZERO);
+    let rebuilt = app.world().resource::<LoadedChunks>().get(IVec2::ZERO);
+    assert!(rebuilt.is_some());
+    assert_ne!(rebuilt, chunk);
+}
+
+#[test]
+fn far_chunks_are_drawn_with_less_detail() {
+    let mut app = app();
+    app.insert_resource(TileLod::default());
+    move_player(&mut app, Vec3::ZERO);
+    for _ in 0..10 {
+        app.update();
+    }
+    let lod = |app: &mut App, coord: IVec2| {
+        let entity = app.world().resource::<LoadedChunks>().get(coord).unwrap();
+        app.world().get::<Chunk>(entity).unwrap().lod
+    };
+    // The view radius is 40 units and the closest tile of chunk (1, 0) is 32 units away.
+    assert_eq!(lod(&mut app, IVec2::ZERO), Lod::Near);
+    assert_eq!(lod(&mut app, IVec2::X), Lod::Far);
+
+    move_player(&mut app, Vec3::new(40.0, 0.0, 0.0));
+    for _ in 0..10 {
+        app.update();
+    }
+    assert_eq!(lod(&mut app, IVec2::X), Lod::Near);
+}
+
+/// How many chunks were spawned or redrawn in the last frame.
+#[derive(Resource, Default)]
+struct DrawnChunks(usize);
+
+fn count_drawn_chunks(chunks: Query<(), Changed<ChunkTiles>>, mut drawn: ResMut<DrawnChunks>) {
+    drawn.0 = chunks.iter().count();
+}
+
+#[test]
+fn redrawn_chunks_share_the_spawn_budget() {
+    let mut app = app();
+    app.insert_resource(TileLod::default())
+        .insert_resource(Kare::new(60, 4).unwrap())
+        .init_resource::<DrawnChunks>()
+        .add_systems(PostUpdate, count_drawn_chunks);
+    move_player(&mut app, Vec3::ZERO);
+    for _ in 0..20 {
+        app.update();
+    }
+
+    // Moving brings new chunks into view and changes the detail of many others.
+    place_player(&mut app, Vec3::new(64.0, 0.0, 64.0));
+    let budget = (TILE_SPAWN_BUDGET / (CHUNK_SIZE * CHUNK_SIZE)).max(1);
+    let mut most = 0;
+    for _ in 0..10_000 {
+        app.update();
+        let drawn = app.world().resource::<DrawnChunks>().0;
+        assert!(drawn <= budget, "{drawn} chunks drawn in one frame");
+        most = most.max(drawn);
+        if drawn == 0 && app.world().resource::<PendingChunks>().is_empty() {
+            break;
+        }
+        std::thread::yield_now();
+    }
+    assert_eq!(most, budget);
+}
+
+#[test]
+fn the_ground_collider_follows_the_terrain() {
+    let mut app = app();
+    move_player(&mut app, Vec3::ZERO);
+    let rules = app.world().resource::<WFCRules>().clone();
+    let world = app.world_mut();
+    let mut chunks = world.query::<(&Chunk, &Children)>();
+    let mut colliders = world.query::<(&Collider, &Transform)>();
+
+    let mut heights = HashSet::new();
+    for (chunk, children) in chunks.iter(world) {
+        let (collider, transform) = children
+            .iter()
+            .filter_map(|&child| colliders.get(world, child).ok())
+            .find(|(collider, _)| collider.as_heightfield().is_some())
+            .expect("the chunk has no ground");
+        for z in 0..=CHUNK_SIZE as i32 {
+            for x in 0..=CHUNK_SIZE as i32 {
+                let corner = IVec2::new(x, z);
+                // Corners sit half a tile before the center of their cell. Stay just
+                // inside the chunk so that the ray hits it.
+                let position =
+                    ((corner.as_vec2() - 0.5) * 4.0).clamp(Vec2::splat(-1.99), Vec2::splat(29.99));
+                let origin = Vec3::new(position.x, 100.0, position.y) - transform.translation;
+                let distance = collider
+                    .cast_local_ray(origin, Vec3::NEG_Y, 1000.0, true)
+                    .expect("the ray missed the ground");
+                let level = elevation::corner_level(
+                    &rules,
+                    MapSeed(7),
+                    chunk.coord * CHUNK_SIZE as i32 + corner,
+                );
+                let expected = level as f32 * LEVEL_RISE * 4.0;
+                assert!(
+                    (100.0 - distance - expected).abs() < 0.05,
+                    "the ground of chunk {} is at {} instead of {expected} at corner {corner}",
+                    chunk.coord,
+                    100.0 - distance
+                );
+                heights.insert(level);
+            }
+        }
+    }
+    assert!(heights.len() > 1, "the ground around the origin is flat");
+}
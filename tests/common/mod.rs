@@ -0,0 +1,15 @@
+//! Fixtures shared by the integration tests.
+// Every test crate compiles this module, but not all of them use every fixture.
+#![allow(dead_code)]
+
+use bevy_template::mapgen::{tileset::Tileset, WFCRules};
+
+/// The tileset the game ships with.
+pub fn default_tileset() -> Tileset {
+    Tileset::from_ron(include_bytes!("../../assets/tilesets/default.tileset.ron")).unwrap()
+}
+
+/// The rules of [`default_tileset`].
+pub fn default_rules() -> WFCRules {
+    WFCRules::from_tileset(&default_tileset())
+}
@@ -0,0 +1,167 @@
+use bevy::{
+    input::{
+        gamepad::{
+            GamepadButtonChangedEvent, GamepadConnection, GamepadConnectionEvent, GamepadEvent,
+            GamepadInfo, GamepadRumbleRequest,
+        },
+        InputPlugin,
+    },
+    prelude::*,
+    scene::ScenePlugin,
+    time::TimeUpdateStrategy,
+};
+use bevy_rapier3d::prelude::*;
+use bevy_template::game::{
+    crash::{self, Crash},
+    input::{self, BindingsFile},
+    map_change::Obstacle,
+    spawn::player::Player,
+};
+use std::time::Duration;
+
+#[derive(Resource, Default)]
+struct Crashes(Vec<Crash>);
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        AssetPlugin::default(),
+        InputPlugin,
+        ScenePlugin,
+        RapierPhysicsPlugin::<NoUserData>::default(),
+        input::plugin,
+        crash::plugin,
+    ))
+    .init_asset::<Mesh>()
+    .insert_resource(BindingsFile(None))
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        16,
+    )))
+    .init_resource::<Crashes>()
+    .observe(|trigger: Trigger<Crash>, mut crashes: ResMut<Crashes>| {
+        crashes.0.push(*trigger.event());
+    });
+    app.world_mut().spawn((
+        Player,
+        TransformBundle::from_transform(Transform::from_xyz(0.0, 1.0, 0.0)),
+        RigidBody::KinematicPositionBased,
+        Collider::cuboid(0.5, 1.0, 0.5),
+        KinematicCharacterController::default(),
+    ));
+    app
+}
+
+/// Moves the player by `step` every frame for a second.
+fn ride(app: &mut App, step: Vec3) {
+    for _ in 0..60 {
+        let mut controller = app
+            .world_mut()
+            .query_filtered::<&mut KinematicCharacterController, With<Player>>();
+        controller.single_mut(app.world_mut()).translation = Some(step);
+        app.update();
+    }
+}
+
+const FORWARD: Vec3 = Vec3::new(0.0, 0.0, -0.2);
+
+fn player_z(app: &mut App) -> f32 {
+    let mut player = app.world_mut().query_filtered::<&Transform, With<Player>>();
+    player.single(app.world()).translation.z
+}
+
+#[test]
+fn riding_into_an_obstacle_crashes_once() {
+    let mut app = app();
+    let wall = app
+        .world_mut()
+        .spawn((
+            Obstacle,
+            Collider::cuboid(5.0, 2.0, 0.5),
+            TransformBundle::from_transform(Transform::from_xyz(0.0, 1.0, -5.0)),
+        ))
+        .id();
+    ride(&mut app, FORWARD);
+
+    let crashes = &app.world().resource::<Crashes>().0;
+    assert_eq!(crashes.len(), 1, "{crashes:?}");
+    assert_eq!(crashes[0].obstacle, wall);
+    assert!(crashes[0].speed > 0.0);
+    assert!(
+        player_z(&mut app) > -4.5,
+        "the player rode through the wall"
+    );
+}
+
+#[test]
+fn touching_other_colliders_is_not_a_crash() {
+    let mut app = app();
+    app.world_mut().spawn((
+        Collider::cuboid(5.0, 2.0, 0.5),
+        TransformBundle::from_transform(Transform::from_xyz(0.0, 1.0, -5.0)),
+    ));
+    ride(&mut app, FORWARD);
+    assert!(app.world().resource::<Crashes>().0.is_empty());
+}
+
+#[test]
+fn falling_does_not_add_to_the_crash_speed() {
+    let mut app = app();
+    app.world_mut().spawn((
+        Collider::cuboid(50.0, 0.5, 50.0),
+        TransformBundle::from_transform(Transform::from_xyz(0.0, -0.5, 0.0)),
+    ));
+    app.world_mut().spawn((
+        Obstacle,
+        Collider::cuboid(5.0, 2.0, 0.5),
+        TransformBundle::from_transform(Transform::from_xyz(0.0, 1.0, -5.0)),
+    ));
+    ride(&mut app, FORWARD + Vec3::new(0.0, -0.5, 0.0));
+
+    let crashes = &app.world().resource::<Crashes>().0;
+    assert_eq!(crashes.len(), 1, "{crashes:?}");
+    let step = app.world().resource::<Time<Fixed>>().timestep();
+    let expected = FORWARD.length() / step.as_secs_f32();
+    assert!(
+        (crashes[0].speed - expected).abs() < 0.01,
+        "crashed at {} instead of {expected}",
+        crashes[0].speed
+    );
+}
+
+#[test]
+fn crashes_rumble_the_players_gamepad() {
+    let mut app = app();
+    let (first, second) = (Gamepad::new(0), Gamepad::new(1));
+    for gamepad in [first, second] {
+        app.world_mut()
+            .send_event(GamepadEvent::Connection(GamepadConnectionEvent::new(
+                gamepad,
+                GamepadConnection::Connected(GamepadInfo {
+                    name: "Test Gamepad".to_string(),
+                }),
+            )));
+    }
+    app.update();
+    // The player rides with the second gamepad.
+    app.world_mut()
+        .send_event(GamepadEvent::Button(GamepadButtonChangedEvent::new(
+            second,
+            GamepadButtonType::RightTrigger2,
+            1.0,
+        )));
+    app.update();
+    let obstacle = app.world_mut().spawn(Obstacle).id();
+    app.world_mut().trigger(Crash {
+        obstacle,
+        speed: 20.0,
+    });
+
+    let events = app.world().resource::<Events<GamepadRumbleRequest>>();
+    let mut reader = events.get_reader();
+    let rumbles: Vec<_> = reader.read(events).collect();
+    assert_eq!(rumbles.len(), 1);
+    assert_eq!(rumbles[0].gamepad(), second);
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use bevy::math::IVec2;
+use bevy_template::mapgen::{
+    elevation::{self, MAX_LEVEL},
+    generate_chunk,
+    tileset::Tileset,
+    MapSeed, TileType, WFCRules, CHUNK_SIZE,
+};
+use common::default_rules;
+
+#[test]
+fn neighbouring_cells_are_at_most_one_level_apart() {
+    let rules = default_rules();
+    let seed = MapSeed(4);
+    let mut levels = [false; MAX_LEVEL as usize + 1];
+    for x in -200..200 {
+        for z in -200..200 {
+            let cell = IVec2::new(x, z);
+            let level = elevation::level_at(&rules, seed, cell);
+            levels[level as usize] = true;
+            for neighbour in [IVec2::X, IVec2::Y, IVec2::ONE, IVec2::new(1, -1)] {
+                let difference = elevation::level_at(&rules, seed, cell + neighbour) - level;
+                assert!(
+                    difference.abs() <= 1,
+                    "{cell} and {} are too far apart",
+                    cell + neighbour
+                );
+            }
+        }
+    }
+    assert_eq!(levels, [true; MAX_LEVEL as usize + 1]);
+}
+
+#[test]
+fn only_slope_tiles_are_placed_on_slopes() {
+    let rules = default_rules();
+    let seed = MapSeed(4);
+    let mut slopes = 0;
+    for x in -6..6 {
+        for z in -6..6 {
+            let coord = IVec2::new(x, z);
+            let grid = generate_chunk(&rules, seed, coord).unwrap().grid;
+            for (x, z, tile) in grid.iter() {
+                let cell = coord * CHUNK_SIZE as i32 + IVec2::new(x as i32, z as i32);
+                if elevation::is_slope(&rules, seed, cell) {
+                    slopes += 1;
+                    assert!(
+                        rules.fits_slope(TileType::from_id(tile)),
+                        "tile {tile} on the slope at {cell}"
+                    );
+                }
+            }
+        }
+    }
+    assert!(slopes > 0);
+}
+
+#[test]
+fn level_cells_have_level_corners() {
+    let rules = default_rules();
+    let seed = MapSeed(4);
+    for x in -50..50 {
+        for z in -50..50 {
+            let cell = IVec2::new(x, z);
+            if elevation::is_slope(&rules, seed, cell) {
+                continue;
+            }
+            let level = elevation::level_at(&rules, seed, cell);
+            for corner in [IVec2::ZERO, IVec2::X, IVec2::Y, IVec2::ONE] {
+                assert_eq!(elevation::corner_level(&rules, seed, cell + corner), level);
+            }
+        }
+    }
+}
+
+#[test]
+fn tilesets_without_slope_tiles_are_flat() {
+    let tileset = include_str!("../assets/tilesets/default.tileset.ron")
+        .replace("slope: true", "slope: false");
+    let rules = WFCRules::from_tileset(&Tileset::from_ron(tileset.as_bytes()).unwrap());
+    assert!(!rules.has_slopes());
+    for x in -50..50 {
+        let cell = IVec2::new(x * 7, x * 3);
+        assert_eq!(elevation::level_at(&rules, MapSeed(4), cell), 0);
+        assert!(!elevation::is_slope(&rules, MapSeed(4), cell));
+    }
+}
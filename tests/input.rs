@@ -0,0 +1,401 @@
+use bevy::{
+    input::{
+        gamepad::{
+            GamepadAxisChangedEvent, GamepadButtonChangedEvent, GamepadConnection,
+            GamepadConnectionEvent, GamepadEvent, GamepadInfo,
+        },
+        keyboard::{Key, KeyboardInput},
+        ButtonState, InputPlugin,
+    },
+    prelude::*,
+};
+use bevy_template::game::input::{
+    self, Action, ActionState, ActiveGamepad, Binding, Bindings, BindingsError, BindingsFile,
+    RebindError, Rebinding,
+};
+
+const GAMEPAD: Gamepad = Gamepad { id: 0 };
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InputPlugin, input::plugin))
+        .insert_resource(BindingsFile(None));
+    app
+}
+
+fn connect_gamepad(app: &mut App) {
+    app.world_mut()
+        .send_event(GamepadEvent::Connection(GamepadConnectionEvent::new(
+            GAMEPAD,
+            GamepadConnection::Connected(GamepadInfo {
+                name: "Test Gamepad".to_string(),
+            }),
+        )));
+    app.update();
+}
+
+/// Pulls `button` as far as `value`, the way the gamepad backend reports it.
+fn pull(app: &mut App, button: GamepadButtonType, value: f32) {
+    app.world_mut()
+        .resource_mut::<Axis<GamepadButton>>()
+        .set(GamepadButton::new(GAMEPAD, button), value);
+    app.world_mut()
+        .send_event(GamepadEvent::Button(GamepadButtonChangedEvent::new(
+            GAMEPAD, button, value,
+        )));
+}
+
+fn push(app: &mut App, axis: GamepadAxisType, value: f32) {
+    app.world_mut()
+        .send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(
+            GAMEPAD, axis, value,
+        )));
+}
+
+fn press(app: &mut App, key: KeyCode) {
+    let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+    keys.clear();
+    keys.press(key);
+}
+
+#[test]
+fn every_action_has_a_default_binding() {
+    let bindings = Bindings::default();
+    for action in Action::ALL {
+        assert!(!bindings.get(action).is_empty(), "{action:?}");
+    }
+}
+
+#[test]
+fn actions_follow_their_bindings() {
+    let mut app = app();
+    press(&mut app, KeyCode::ArrowUp);
+    app.update();
+    let actions = app.world().resource::<ActionState>();
+    assert!(actions.pressed(Action::Throttle));
+    assert!(actions.just_pressed(Action::Throttle));
+    assert_eq!(actions.value(Action::Brake), 0.0);
+
+    app.update();
+    let actions = app.world().resource::<ActionState>();
+    assert!(actions.pressed(Action::Throttle));
+    assert!(!actions.just_pressed(Action::Throttle));
+}
+
+#[test]
+fn rebound_keys_trigger_their_new_action() {
+    let mut app = app();
+    app.world_mut()
+        .resource_mut::<Bindings>()
+        .rebind(Action::Boost, Binding::Key(KeyCode::KeyW))
+        .unwrap();
+    press(&mut app, KeyCode::KeyW);
+    app.update();
+    let actions = app.world().resource::<ActionState>();
+    assert!(actions.pressed(Action::Boost));
+    assert!(!actions.pressed(Action::Throttle));
+}
+
+#[test]
+fn rebinding_replaces_the_keys_of_the_action() {
+    let mut bindings = Bindings::default();
+    bindings
+        .rebind(Action::SteerLeft, Binding::Key(KeyCode::KeyQ))
+        .unwrap();
+    let keys: Vec<_> = bindings
+        .get(Action::SteerLeft)
+        .iter()
+        .filter(|binding| matches!(binding, Binding::Key(_)))
+        .collect();
+    assert_eq!(keys, [&Binding::Key(KeyCode::KeyQ)]);
+
+    bindings
+        .rebind(Action::Brake, Binding::Key(KeyCode::KeyQ))
+        .unwrap();
+    assert!(!bindings
+        .get(Action::SteerLeft)
+        .contains(&Binding::Key(KeyCode::KeyQ)));
+}
+
+#[test]
+fn rebinding_a_gamepad_input_keeps_the_keys() {
+    let mut bindings = Bindings::default();
+    let button = Binding::GamepadButton(GamepadButtonType::RightTrigger);
+    bindings.rebind(Action::Throttle, button).unwrap();
+    assert_eq!(
+        bindings.get(Action::Throttle),
+        &[
+            Binding::Key(KeyCode::KeyW),
+            Binding::Key(KeyCode::ArrowUp),
+            button
+        ]
+    );
+}
+
+#[test]
+fn triggers_hold_actions_partway() {
+    let mut app = app();
+    connect_gamepad(&mut app);
+    pull(&mut app, GamepadButtonType::RightTrigger2, 0.5);
+    app.update();
+    let actions = app.world().resource::<ActionState>();
+    assert_eq!(actions.value(Action::Throttle), 0.5);
+    assert!(!actions.pressed(Action::Throttle));
+
+    pull(&mut app, GamepadButtonType::RightTrigger2, 1.0);
+    pull(&mut app, GamepadButtonType::LeftTrigger2, 0.25);
+    app.update();
+    let actions = app.world().resource::<ActionState>();
+    assert!(actions.just_pressed(Action::Throttle));
+    assert_eq!(actions.value(Action::Brake), 0.25);
+}
+
+#[test]
+fn the_left_stick_steers_both_ways() {
+    let mut app = app();
+    connect_gamepad(&mut app);
+    push(&mut app, GamepadAxisType::LeftStickX, -0.5);
+    app.update();
+    let actions = app.world().resource::<ActionState>();
+    assert_eq!(actions.value(Action::SteerLeft), 0.5);
+    assert_eq!(actions.value(Action::SteerRight), 0.0);
+
+    push(&mut app, GamepadAxisType::LeftStickX, 0.75);
+    app.update();
+    let actions = app.world().resource::<ActionState>();
+    assert_eq!(actions.value(Action::SteerLeft), 0.0);
+    assert_eq!(actions.value(Action::SteerRight), 0.75);
+}
+
+#[test]
+fn gamepad_bindings_are_named_like_the_gamepad() {
+    assert_eq!(
+        Binding::GamepadButton(GamepadButtonType::South).to_string(),
+        "A"
+    );
+    assert_eq!(
+        Binding::GamepadButton(GamepadButtonType::RightTrigger2).to_string(),
+        "RT"
+    );
+    let stick = Binding::GamepadAxis {
+        axis: GamepadAxisType::LeftStickX,
+        negative: true,
+    };
+    assert_eq!(stick.to_string(), "Left Stick Left");
+}
+
+#[test]
+fn bindings_are_saved_and_loaded() {
+    let path = std::env::temp_dir()
+        .join(format!("bindings-{}", std::process::id()))
+        .join("bindings.ron");
+    let mut bindings = Bindings::default();
+    bindings
+        .rebind(Action::Pause, Binding::Key(KeyCode::KeyP))
+        .unwrap();
+    bindings
+        .rebind(
+            Action::Boost,
+            Binding::GamepadAxis {
+                axis: GamepadAxisType::RightStickY,
+                negative: false,
+            },
+        )
+        .unwrap();
+    bindings.save(&path).unwrap();
+    let loaded = Bindings::load(&path);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    assert_eq!(loaded.unwrap(), bindings);
+}
+
+#[test]
+fn actions_missing_from_a_file_keep_their_defaults() {
+    let path = std::env::temp_dir().join(format!("partial-bindings-{}.ron", std::process::id()));
+    std::fs::write(&path, "{ Pause: [Key(KeyP)] }").unwrap();
+    let loaded = Bindings::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    let loaded = loaded.unwrap();
+    assert_eq!(loaded.get(Action::Pause), &[Binding::Key(KeyCode::KeyP)]);
+    assert_eq!(
+        loaded.get(Action::Throttle),
+        Bindings::default().get(Action::Throttle)
+    );
+}
+
+/// Loads bindings from a file holding `text`.
+fn load(name: &str, text: &str) -> Result<Bindings, BindingsError> {
+    let path = std::env::temp_dir().join(format!("{name}-{}.ron", std::process::id()));
+    std::fs::write(&path, text).unwrap();
+    let loaded = Bindings::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    loaded
+}
+
+#[test]
+fn files_binding_an_input_twice_are_rejected() {
+    assert!(matches!(
+        load("duplicate-bindings", "{ Boost: [Key(KeyW)] }"),
+        Err(BindingsError::Duplicate(Binding::Key(KeyCode::KeyW)))
+    ));
+    assert!(matches!(
+        load("repeated-bindings", "{ Boost: [Key(KeyB), Key(KeyB)] }"),
+        Err(BindingsError::Duplicate(Binding::Key(KeyCode::KeyB)))
+    ));
+}
+
+#[test]
+fn files_without_a_pause_binding_are_rejected() {
+    assert!(matches!(
+        load("unbound-pause", "{ Pause: [] }"),
+        Err(BindingsError::Unbound(Action::Pause))
+    ));
+}
+
+#[test]
+fn unusable_files_fall_back_to_the_defaults() {
+    let path = std::env::temp_dir().join(format!("unusable-bindings-{}.ron", std::process::id()));
+    std::fs::write(&path, "{ Pause: [] }").unwrap();
+    let mut app = app();
+    app.insert_resource(BindingsFile(Some(path.clone())));
+    app.update();
+    app.update();
+    let text = std::fs::read_to_string(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(*app.world().resource::<Bindings>(), Bindings::default());
+    assert_eq!(text.unwrap(), "{ Pause: [] }");
+}
+
+#[test]
+fn loading_the_bindings_does_not_save_them() {
+    let path = std::env::temp_dir().join(format!("loaded-bindings-{}.ron", std::process::id()));
+    std::fs::write(&path, "{ Boost: [Key(KeyB)] }").unwrap();
+    let mut app = app();
+    app.insert_resource(BindingsFile(Some(path.clone())));
+    app.update();
+    app.update();
+    let text = std::fs::read_to_string(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        app.world().resource::<Bindings>().get(Action::Boost),
+        &[Binding::Key(KeyCode::KeyB)]
+    );
+    assert_eq!(text.unwrap(), "{ Boost: [Key(KeyB)] }");
+}
+
+#[test]
+fn rebinding_never_leaves_pause_without_a_key() {
+    let mut bindings = Bindings::default();
+    bindings
+        .rebind(Action::Pause, Binding::Key(KeyCode::KeyP))
+        .unwrap();
+    let taken = bindings.rebind(Action::Boost, Binding::Key(KeyCode::KeyP));
+    assert_eq!(taken, Err(RebindError(Action::Pause)));
+    assert_eq!(
+        bindings.get(Action::Pause),
+        &[
+            Binding::GamepadButton(GamepadButtonType::Start),
+            Binding::Key(KeyCode::KeyP)
+        ]
+    );
+    assert!(!bindings
+        .get(Action::Boost)
+        .contains(&Binding::Key(KeyCode::KeyP)));
+
+    let start = Binding::GamepadButton(GamepadButtonType::Start);
+    assert_eq!(
+        bindings.rebind(Action::Throttle, start),
+        Err(RebindError(Action::Pause))
+    );
+}
+
+/// Types `key` on the keyboard, as the window would report it.
+fn type_key(app: &mut App, key: KeyCode, logical_key: Key) {
+    for state in [ButtonState::Pressed, ButtonState::Released] {
+        app.world_mut().send_event(KeyboardInput {
+            key_code: key,
+            logical_key: logical_key.clone(),
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+        app.update();
+    }
+}
+
+/// An app waiting for an input to bind to `action`.
+fn rebinding_app(action: Action) -> App {
+    let mut app = app();
+    connect_gamepad(&mut app);
+    app.world_mut().resource_mut::<Rebinding>().0 = Some(action);
+    app.update();
+    app
+}
+
+#[test]
+fn rebinding_takes_the_next_input() {
+    let mut app = rebinding_app(Action::Boost);
+    type_key(&mut app, KeyCode::KeyB, Key::Character("b".into()));
+    assert_eq!(app.world().resource::<Rebinding>().0, None);
+    assert!(app
+        .world()
+        .resource::<Bindings>()
+        .get(Action::Boost)
+        .contains(&Binding::Key(KeyCode::KeyB)));
+}
+
+#[test]
+fn escape_and_east_cancel_rebinding() {
+    let mut app = rebinding_app(Action::Throttle);
+    type_key(&mut app, KeyCode::Escape, Key::Escape);
+    assert_eq!(app.world().resource::<Rebinding>().0, None);
+    assert_eq!(*app.world().resource::<Bindings>(), Bindings::default());
+
+    let mut app = rebinding_app(Action::Throttle);
+    pull(&mut app, GamepadButtonType::East, 1.0);
+    app.update();
+    assert_eq!(app.world().resource::<Rebinding>().0, None);
+    assert_eq!(*app.world().resource::<Bindings>(), Bindings::default());
+}
+
+#[test]
+fn rebinding_keeps_waiting_for_an_input_it_can_take() {
+    let mut app = rebinding_app(Action::Boost);
+    pull(&mut app, GamepadButtonType::Start, 1.0);
+    app.update();
+    assert_eq!(app.world().resource::<Rebinding>().0, Some(Action::Boost));
+    assert_eq!(*app.world().resource::<Bindings>(), Bindings::default());
+}
+
+#[test]
+fn the_last_gamepad_used_is_active() {
+    let mut app = app();
+    connect_gamepad(&mut app);
+    assert_eq!(app.world().resource::<ActiveGamepad>().0, Some(GAMEPAD));
+
+    let other = Gamepad::new(1);
+    app.world_mut()
+        .send_event(GamepadEvent::Connection(GamepadConnectionEvent::new(
+            other,
+            GamepadConnection::Connected(GamepadInfo {
+                name: "Other Gamepad".to_string(),
+            }),
+        )));
+    app.update();
+    assert_eq!(app.world().resource::<ActiveGamepad>().0, Some(GAMEPAD));
+
+    app.world_mut()
+        .send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(
+            other,
+            GamepadAxisType::LeftStickX,
+            -1.0,
+        )));
+    app.update();
+    assert_eq!(app.world().resource::<ActiveGamepad>().0, Some(other));
+
+    app.world_mut()
+        .send_event(GamepadEvent::Connection(GamepadConnectionEvent::new(
+            other,
+            GamepadConnection::Disconnected,
+        )));
+    app.update();
+    assert_eq!(app.world().resource::<ActiveGamepad>().0, None);
+}
@@ -0,0 +1,45 @@
+use bevy_template::game::lod::{Lod, TileLod};
+
+const RADIUS: f32 = 100.0;
+
+#[test]
+fn detail_drops_with_distance() {
+    let lod = TileLod::default();
+    assert_eq!(lod.lod(None, 0.0, RADIUS), Lod::Near);
+    assert_eq!(lod.lod(None, 60.0, RADIUS), Lod::Mid);
+    assert_eq!(lod.lod(None, 90.0, RADIUS), Lod::Far);
+}
+
+#[test]
+fn detail_only_changes_well_past_a_boundary() {
+    let lod = TileLod::default();
+    let boundary = lod.near * RADIUS;
+    let margin = lod.hysteresis * RADIUS;
+
+    // Moving away keeps the detail until the chunk is past the margin.
+    assert_eq!(
+        lod.lod(Some(Lod::Near), boundary + margin / 2.0, RADIUS),
+        Lod::Near
+    );
+    assert_eq!(
+        lod.lod(Some(Lod::Near), boundary + margin * 1.5, RADIUS),
+        Lod::Mid
+    );
+
+    // And coming back, it only returns once the chunk is well inside again.
+    assert_eq!(
+        lod.lod(Some(Lod::Mid), boundary - margin / 2.0, RADIUS),
+        Lod::Mid
+    );
+    assert_eq!(
+        lod.lod(Some(Lod::Mid), boundary - margin * 1.5, RADIUS),
+        Lod::Near
+    );
+}
+
+#[test]
+fn large_jumps_skip_tiers() {
+    let lod = TileLod::default();
+    assert_eq!(lod.lod(Some(Lod::Near), RADIUS, RADIUS), Lod::Far);
+    assert_eq!(lod.lod(Some(Lod::Far), 0.0, RADIUS), Lod::Near);
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use bevy::math::IVec2;
+use bevy_template::mapgen::{
+    generate_area, generate_chunk, output, MapSeed, TileType, WFCRules, CHUNK_SIZE,
+};
+use bevy_template::wfc::{Direction, Ruleset, Solver};
+use common::{default_rules, default_tileset};
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::HashMap;
+
+#[test]
+fn areas_match_the_chunks_they_cover() {
+    let rules = default_rules();
+    let seed = MapSeed(9);
+    let origin = IVec2::new(-5, 3);
+    let area = generate_area(&rules, seed, origin, 20, 13).unwrap();
+
+    for (x, z, tile) in area.iter() {
+        let cell = origin + IVec2::new(x as i32, z as i32);
+        let coord = cell.div_euclid(IVec2::splat(CHUNK_SIZE as i32));
+        let local = (cell - coord * CHUNK_SIZE as i32).as_uvec2();
+        let chunk = generate_chunk(&rules, seed, coord).unwrap().grid;
+        assert_eq!(chunk.get(local.x as usize, local.y as usize), tile);
+    }
+}
+
+#[test]
+fn outputs_have_one_entry_per_cell() {
+    let tileset = default_tileset();
+    let rules = default_rules();
+    let grid = generate_area(&rules, MapSeed(1), IVec2::ZERO, 12, 5).unwrap();
+
+    let ascii = output::ascii(&grid, &tileset);
+    assert_eq!(ascii.lines().count(), 5);
+    assert!(ascii.lines().all(|line| line.chars().count() == 12));
+
+    let csv = output::csv(&grid);
+    assert_eq!(csv.lines().count(), 5);
+    assert!(csv.lines().all(|line| line.split(',').count() == 12));
+
+    let mut png = Vec::new();
+    output::png(&grid, &tileset, 3, &mut png).unwrap();
+    let info = png::Decoder::new(png.as_slice()).read_info().unwrap();
+    assert_eq!((info.info().width, info.info().height), (36, 15));
+}
+
+#[test]
+fn areas_of_any_size_follow_the_rules() {
+    let rules = default_rules();
+    for size in 0..64 {
+        let (width, depth) = (size, (size * 7 + 3) % 64);
+        let origin = IVec2::new(size as i32 * 5 - 150, 40 - size as i32 * 3);
+        let grid = generate_area(&rules, MapSeed(size as u32), origin, width, depth).unwrap();
+        assert_eq!((grid.width(), grid.depth()), (width, depth));
+
+        for (x, z, tile) in grid.iter() {
+            let tile = TileType::from_id(tile);
+            if x + 1 < width {
+                let east = TileType::from_id(grid.get(x + 1, z));
+                assert!(
+                    rules.allows(tile, Direction::East, east),
+                    "{tile:?} and {east:?} at ({x}, {z}) of a {width}x{depth} area"
+                );
+            }
+            if z + 1 < depth {
+                let south = TileType::from_id(grid.get(x, z + 1));
+                assert!(
+                    rules.allows(tile, Direction::South, south),
+                    "{tile:?} and {south:?} at ({x}, {z}) of a {width}x{depth} area"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn every_tile_is_placed_somewhere() {
+    let rules = default_rules();
+    let grid = generate_area(&rules, MapSeed(5), IVec2::splat(-96), 192, 192).unwrap();
+    let mut placed = vec![false; rules.tile_count()];
+    for (_, _, tile) in grid.iter() {
+        placed[tile] = true;
+    }
+    let missing: Vec<usize> = (0..rules.tile_count())
+        .filter(|&tile| !placed[tile])
+        .collect();
+    assert!(missing.is_empty(), "tiles {missing:?} were never placed");
+}
+
+#[test]
+fn missing_rules_fail_without_panicking() {
+    let rules = WFCRules::default();
+    for size in 0..64 {
+        let result = generate_area(&rules, MapSeed(0), IVec2::ZERO, size, size);
+        assert_eq!(result.is_ok(), size == 0, "{size}x{size} area");
+
+        let ruleset = Ruleset::new(0);
+        let result = Solver::new(&ruleset, size, size, StdRng::seed_from_u64(0)).run();
+        assert_eq!(result.is_ok(), size == 0, "{size}x{size} grid");
+    }
+}
+
+#[test]
+fn contradictions_find_tiles_that_do_not_fit() {
+    let rules = WFCRules::from_tileset(&default_tileset());
+    let grid = generate_area(&rules, MapSeed(3), IVec2::splat(-24), 48, 48).unwrap();
+    let mut tiles: HashMap<IVec2, TileType> = grid
+        .iter()
+        .map(|(x, z, tile)| (IVec2::new(x as i32, z as i32), TileType::from_id(tile)))
+        .collect();
+    assert!(rules.contradictions(&tiles).is_empty());
+
+    let (cell, east) = (IVec2::new(10, 10), IVec2::new(11, 10));
+    let misfit = (0..rules.tile_count())
+        .map(TileType::from_id)
+        .find(|&tile| !rules.allows(tile, Direction::East, tiles[&east]))
+        .unwrap();
+    tiles.insert(cell, misfit);
+    let contradictions = rules.contradictions(&tiles);
+    assert!(contradictions.contains(&cell));
+    assert!(contradictions.contains(&east));
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use bevy::math::IVec2;
+use bevy_template::mapgen::{generate_chunk, road, MapSeed, TileType, CHUNK_SIZE};
+use common::default_rules;
+use std::collections::{HashSet, VecDeque};
+
+fn chunks() -> impl Iterator<Item = IVec2> {
+    (-6..6).flat_map(|x| (-6..6).map(move |z| IVec2::new(x, z)))
+}
+
+#[test]
+fn road_network_is_connected() {
+    for seed in 0..8 {
+        let seed = MapSeed(seed);
+        let roads: HashSet<IVec2> = chunks()
+            .flat_map(|coord| road::road_cells(seed, coord))
+            .collect();
+
+        let start = *roads.iter().next().unwrap();
+        let mut reached = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(cell) = queue.pop_front() {
+            for offset in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+                let next = cell + offset;
+                if roads.contains(&next) && reached.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        assert_eq!(
+            reached.len(),
+            roads.len(),
+            "seed {} has separate roads",
+            seed.0
+        );
+    }
+}
+
+#[test]
+fn generated_chunks_keep_their_road_open() {
+    let rules = default_rules();
+    let seed = MapSeed(3);
+    for coord in chunks() {
+        let grid = generate_chunk(&rules, seed, coord).unwrap().grid;
+        let roads = road::road_cells(seed, coord);
+        assert!(
+            road::corridor_is_open(&grid, coord * CHUNK_SIZE as i32, &roads, |tile| {
+                rules.is_drivable(TileType::from_id(tile))
+            }),
+            "the road through chunk {coord} is blocked"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Compares generated maps to the snapshots in `tests/snapshots`, so that changes
+//! to the generator or the default tileset that move tiles around are noticed.
+//!
+//! After an intended change, regenerate the snapshots with
+//! `UPDATE_SNAPSHOTS=1 cargo test --test snapshots` and review the diff.
+
+mod common;
+
+use bevy::math::IVec2;
+use bevy_template::mapgen::{generate_area, output, MapSeed};
+use common::default_rules;
+use std::path::PathBuf;
+
+const SIZE: usize = 32;
+
+fn snapshot(seed: u32) -> String {
+    let rules = default_rules();
+    let origin = IVec2::splat(-(SIZE as i32) / 2);
+    output::csv(&generate_area(&rules, MapSeed(seed), origin, SIZE, SIZE).unwrap())
+}
+
+fn assert_snapshot(seed: u32) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("seed-{seed}.csv"));
+    let actual = snapshot(seed);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|error| panic!("could not read {}: {error}", path.display()));
+    let expected = expected.replace("\r\n", "\n");
+    for (z, (actual, expected)) in actual.lines().zip(expected.lines()).enumerate() {
+        assert_eq!(
+            actual,
+            expected,
+            "row {z} of seed {seed} differs from {}",
+            path.display()
+        );
+    }
+    assert_eq!(
+        actual.lines().count(),
+        expected.lines().count(),
+        "seed {seed} has a different size than {}",
+        path.display()
+    );
+}
+
+#[test]
+fn seed_0_matches_snapshot() {
+    assert_snapshot(0);
+}
+
+#[test]
+fn seed_1_matches_snapshot() {
+    assert_snapshot(1);
+}
+
+#[test]
+fn seed_42_matches_snapshot() {
+    assert_snapshot(42);
+}
+
+#[test]
+fn seed_31337_matches_snapshot() {
+    assert_snapshot(31337);
+}
+
+#[test]
+fn generation_is_deterministic() {
+    assert_eq!(snapshot(7), snapshot(7));
+}
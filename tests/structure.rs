@@ -0,0 +1,46 @@
+mod common;
+
+use bevy::math::IVec2;
+use bevy_template::mapgen::{generate_chunk, structure::placement, MapSeed, CHUNK_SIZE};
+use common::default_rules;
+
+#[test]
+fn structures_are_generated_whole() {
+    let rules = default_rules();
+    let seed = MapSeed(3);
+    let mut placed = vec![0; rules.structures().len()];
+    for x in -12..12 {
+        for z in -12..12 {
+            let coord = IVec2::new(x, z);
+            let Some(placement) = placement(&rules, seed, coord) else {
+                continue;
+            };
+            placed[placement.structure] += 1;
+            let grid = generate_chunk(&rules, seed, coord).unwrap().grid;
+            for (cell, tile) in placement.tiles {
+                let local = cell - coord * CHUNK_SIZE as i32;
+                assert!(
+                    local.cmpgt(IVec2::ZERO).all()
+                        && local.cmplt(IVec2::splat(CHUNK_SIZE as i32 - 1)).all(),
+                    "structure touches the border of chunk {coord} at {cell}"
+                );
+                assert_eq!(grid.get(local.x as usize, local.y as usize), tile.id());
+            }
+        }
+    }
+    for (structure, count) in rules.structures().iter().zip(placed) {
+        assert!(count > 0, "{} was never placed", structure.id);
+    }
+}
+
+#[test]
+fn placement_only_depends_on_the_seed() {
+    let rules = default_rules();
+    for x in -4..4 {
+        let coord = IVec2::new(x, 2 * x);
+        assert_eq!(
+            placement(&rules, MapSeed(8), coord),
+            placement(&rules, MapSeed(8), coord)
+        );
+    }
+}
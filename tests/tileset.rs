@@ -0,0 +1,140 @@
+use bevy_template::mapgen::tileset::{Tileset, TilesetError};
+use bevy_template::wfc::Direction;
+
+const CORNER: &str = r#"(
+    connections: [("wall", "wall"), ("face", "face")],
+    tiles: [
+        (
+            id: "corner",
+            model: "models/wall-corner.glb#Scene0",
+            sockets: (north: "wall", east: "face", south: "face", west: "wall"),
+            rotate: true,
+            drivable: true,
+        ),
+    ],
+)"#;
+
+#[test]
+fn rotations_turn_sockets_clockwise() {
+    let tileset = Tileset::from_ron(CORNER.as_bytes()).unwrap();
+    let variants = tileset.variants();
+    assert_eq!(variants.len(), 4);
+
+    let walls = |quarter_turns: usize| {
+        Direction::ALL
+            .into_iter()
+            .filter(|&direction| variants[quarter_turns].socket(direction) == "wall")
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(walls(0), [Direction::North, Direction::West]);
+    assert_eq!(walls(1), [Direction::North, Direction::East]);
+    assert_eq!(walls(2), [Direction::East, Direction::South]);
+    assert_eq!(walls(3), [Direction::South, Direction::West]);
+    assert!(variants.iter().all(|variant| variant.weight() == 0.25));
+}
+
+#[test]
+fn unconnected_sockets_are_rejected() {
+    let tileset = CORNER.replace(r#"("face", "face")"#, "");
+    assert!(matches!(
+        Tileset::from_ron(tileset.as_bytes()),
+        Err(TilesetError::UnconnectedSocket { .. })
+    ));
+}
+
+#[test]
+fn duplicate_tiles_are_rejected() {
+    let corner = CORNER.find("        (\n").unwrap();
+    let end = CORNER.find("    ],\n)").unwrap();
+    let mut tileset = CORNER.to_string();
+    tileset.insert_str(end, &CORNER[corner..end]);
+    assert!(matches!(
+        Tileset::from_ron(tileset.as_bytes()),
+        Err(TilesetError::DuplicateTile(id)) if id == "corner"
+    ));
+}
+
+#[test]
+fn weights_must_be_positive() {
+    let tileset = CORNER.replace("rotate: true,", "rotate: true, weight: -1.0,");
+    assert!(matches!(
+        Tileset::from_ron(tileset.as_bytes()),
+        Err(TilesetError::InvalidWeight(id)) if id == "corner"
+    ));
+}
+
+#[test]
+fn empty_tilesets_are_rejected() {
+    assert!(matches!(
+        Tileset::from_ron(b"(connections: [], tiles: [])"),
+        Err(TilesetError::Empty)
+    ));
+}
+
+#[test]
+fn malformed_tilesets_are_rejected() {
+    let tileset = CORNER.replace("sockets:", "sides:");
+    assert!(matches!(
+        Tileset::from_ron(tileset.as_bytes()),
+        Err(TilesetError::Ron(_))
+    ));
+}
+
+#[test]
+fn default_tileset_is_valid() {
+    Tileset::from_ron(include_bytes!("../assets/tilesets/default.tileset.ron")).unwrap();
+}
+
+#[test]
+fn biomes_only_use_defined_tiles() {
+    let tileset = CORNER.replace(
+        "    ],\n)",
+        r#"    ],
+    biomes: [(id: "ruins", ambient: (1.0, 1.0, 1.0), tiles: {"pillar": 1.0})],
+)"#,
+    );
+    assert!(matches!(
+        Tileset::from_ron(tileset.as_bytes()),
+        Err(TilesetError::UnknownBiomeTile { .. })
+    ));
+}
+
+/// `CORNER` with a structure built from the given footprint rows.
+fn with_structure(footprint: &str) -> String {
+    CORNER.replace(
+        "    ],\n)",
+        &format!("    ],\n    structures: [(id: \"ring\", footprint: [{footprint}], chance: 1.0, rotate: true)],\n)"),
+    )
+}
+
+#[test]
+fn structure_tiles_must_fit_together() {
+    let ring = with_structure(r#""corner@2 corner@3", "corner@1 corner""#);
+    let tileset = Tileset::from_ron(ring.as_bytes()).unwrap();
+    assert_eq!(tileset.structures[0].layouts(&tileset).unwrap().len(), 4);
+
+    let open = with_structure(r#""corner corner@3", "corner@1 corner""#);
+    assert!(matches!(
+        Tileset::from_ron(open.as_bytes()),
+        Err(TilesetError::MismatchedStructureTiles { .. })
+    ));
+}
+
+#[test]
+fn structure_footprints_must_be_small_rectangles() {
+    for footprint in [
+        r#""corner@2 corner@3", "corner""#,
+        r#"". .""#,
+        r#""corner . . . . . corner""#,
+        r#""corner@x""#,
+    ] {
+        assert!(matches!(
+            Tileset::from_ron(with_structure(footprint).as_bytes()),
+            Err(TilesetError::InvalidFootprint(_))
+        ));
+    }
+    assert!(matches!(
+        Tileset::from_ron(with_structure(r#""pillar""#).as_bytes()),
+        Err(TilesetError::UnknownStructureTile { .. })
+    ));
+}
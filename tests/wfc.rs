@@ -0,0 +1,147 @@
+mod common;
+
+use bevy::math::IVec2;
+use bevy_template::mapgen::{MapSeed, TileType};
+use bevy_template::wfc::{Contradiction, Direction, Grid, Ruleset, Solver};
+use common::default_rules;
+use rand::{rngs::StdRng, SeedableRng};
+
+fn assert_valid(grid: &Grid, ruleset: &Ruleset) {
+    for (x, z, tile) in grid.iter() {
+        if x + 1 < grid.width() {
+            assert!(ruleset.is_allowed(tile, Direction::East, grid.get(x + 1, z)));
+        }
+        if z + 1 < grid.depth() {
+            assert!(ruleset.is_allowed(tile, Direction::South, grid.get(x, z + 1)));
+        }
+    }
+}
+
+#[test]
+fn default_rules_are_never_violated() {
+    let rules = default_rules();
+    let ruleset = rules.ruleset();
+    for size in 1..24 {
+        for seed in 0..8 {
+            let grid = Solver::new(ruleset, size, size, StdRng::seed_from_u64(seed))
+                .run()
+                .unwrap();
+            for (x, z, tile) in grid.iter() {
+                let tile = TileType::from_id(tile);
+                if x + 1 < size {
+                    let east = TileType::from_id(grid.get(x + 1, z));
+                    assert!(rules.allows(tile, Direction::East, east));
+                }
+                if z + 1 < size {
+                    let south = TileType::from_id(grid.get(x, z + 1));
+                    assert!(rules.allows(tile, Direction::South, south));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn backtracks_out_of_dead_ends() {
+    // Three-colouring a grid: a cell can run out of colours when its
+    // neighbours were collapsed to all three, which forces a backtrack.
+    let mut ruleset = Ruleset::new(3);
+    for a in 0..3 {
+        for b in (0..3).filter(|&b| b != a) {
+            for direction in Direction::ALL {
+                ruleset.allow(a, direction, b);
+            }
+        }
+    }
+    for seed in 0..32 {
+        let grid = Solver::new(&ruleset, 16, 16, StdRng::seed_from_u64(seed))
+            .run()
+            .unwrap();
+        assert_valid(&grid, &ruleset);
+    }
+}
+
+#[test]
+fn respects_directional_rules() {
+    // Tile 1 may only sit east of tile 0, which forces alternating columns.
+    let mut ruleset = Ruleset::new(2);
+    ruleset.allow(0, Direction::East, 1);
+    ruleset.allow(1, Direction::East, 0);
+    ruleset.allow(0, Direction::South, 0);
+    ruleset.allow(1, Direction::South, 1);
+    let grid = Solver::new(&ruleset, 9, 5, StdRng::seed_from_u64(7))
+        .run()
+        .unwrap();
+    assert_valid(&grid, &ruleset);
+    for (x, z, tile) in grid.iter() {
+        assert_eq!(tile, grid.get(x % 2, z % 2));
+    }
+}
+
+#[test]
+fn reports_unsatisfiable_rules() {
+    let ruleset = Ruleset::new(2);
+    let result = Solver::new(&ruleset, 2, 1, StdRng::seed_from_u64(0)).run();
+    assert_eq!(result, Err(Contradiction));
+}
+
+#[test]
+fn empty_grid_is_solved() {
+    let rules = default_rules();
+    let grid = Solver::new(rules.ruleset(), 0, 0, StdRng::seed_from_u64(0))
+        .run()
+        .unwrap();
+    assert_eq!(grid.iter().count(), 0);
+}
+
+#[test]
+fn same_seed_generates_same_grid() {
+    let rules = default_rules();
+    let generate = |seed: MapSeed, cell| {
+        Solver::new(rules.ruleset(), 16, 16, seed.rng_at(cell))
+            .run()
+            .unwrap()
+    };
+    let cell = IVec2::new(-3, 7);
+    assert_eq!(generate(MapSeed(42), cell), generate(MapSeed(42), cell));
+    assert_ne!(generate(MapSeed(42), cell), generate(MapSeed(43), cell));
+    assert_ne!(
+        generate(MapSeed(42), cell),
+        generate(MapSeed(42), IVec2::new(-3, 8))
+    );
+}
+
+#[test]
+fn tiles_follow_their_weights() {
+    let weights = [1.0, 2.0, 5.0];
+    let mut ruleset = Ruleset::new(weights.len());
+    for (a, &weight) in weights.iter().enumerate() {
+        ruleset.set_weight(a, weight);
+        for b in 0..weights.len() {
+            for direction in Direction::ALL {
+                ruleset.allow(a, direction, b);
+            }
+        }
+    }
+
+    let mut counts = [0; 3];
+    for seed in 0..4 {
+        let grid = Solver::new(&ruleset, 48, 48, StdRng::seed_from_u64(seed))
+            .run()
+            .unwrap();
+        for (_, _, tile) in grid.iter() {
+            counts[tile] += 1;
+        }
+    }
+
+    let total: usize = counts.iter().sum();
+    let weight_sum: f32 = weights.iter().sum();
+    for (tile, &count) in counts.iter().enumerate() {
+        let expected = weights[tile] / weight_sum;
+        let observed = count as f32 / total as f32;
+        assert!(
+            (observed - expected).abs() < 0.02,
+            "tile {tile} was picked {observed:.3} of the time, expected {expected:.3}"
+        );
+    }
+}